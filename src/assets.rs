@@ -1,40 +1,150 @@
 use plist;
 
 use std::collections::HashMap;
+use std::io::{BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::constants::Gamemode;
 
 use image::GenericImageView;
 use image::DynamicImage;
 
-/// "{1,2}" -> `(1, 2)`
-fn parse_vec(str: &str) -> (i32, i32) {
-    let parts: Vec<&str> = str[1..str.len()-1].split(",").collect();
-    let a: Vec<i32> = parts
-        .iter()
-        .map(|s| s.trim().parse::<i32>().unwrap())
-        .collect();
+/// Errors that can occur while loading spritesheets and animations.
+#[derive(Debug)]
+pub enum AssetError {
+    /// A file could not be read.
+    Io(std::io::Error),
+    /// A .plist file could not be parsed.
+    Plist(plist::Error),
+    /// A texture could not be decoded.
+    Image(image::ImageError),
+    /// A key is missing from a .plist dictionary, or has the wrong type. `sprite` is the name of the entry (or section) being read.
+    MissingKey { sprite: String, key: String },
+    /// A `{x,y}` string could not be parsed.
+    InvalidVector(String),
+    /// A `{{x,y},{w,h}}` string could not be parsed.
+    InvalidRect(String),
+    /// A number could not be parsed.
+    InvalidNumber(String),
+    /// A sprite's `textureRect` lies outside of its texture.
+    SpriteOutOfBounds { sprite: String },
+    /// An animation frame's key isn't of the form `Robot_run_001.png`.
+    InvalidAnimationKey(String),
+    /// Files required to render icons are missing from a `Resources` directory.
+    MissingFiles(Vec<PathBuf>)
+}
 
-    return (a[0], a[1])
+impl std::fmt::Display for AssetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetError::Io(err) => write!(f, "io error: {}", err),
+            AssetError::Plist(err) => write!(f, "could not parse plist: {}", err),
+            AssetError::Image(err) => write!(f, "could not decode texture: {}", err),
+            AssetError::MissingKey { sprite, key } => write!(f, "`{}` is missing key `{}`", sprite, key),
+            AssetError::InvalidVector(str) => write!(f, "could not parse vector `{}`", str),
+            AssetError::InvalidRect(str) => write!(f, "could not parse rect `{}`", str),
+            AssetError::InvalidNumber(str) => write!(f, "could not parse number `{}`", str),
+            AssetError::SpriteOutOfBounds { sprite } => write!(f, "sprite `{}` lies outside of its texture", sprite),
+            AssetError::InvalidAnimationKey(key) => write!(f, "could not parse animation frame `{}`", key),
+            AssetError::MissingFiles(paths) => write!(f, "missing files: {}", paths.iter().map(|path| path.display().to_string()).collect::<Vec<String>>().join(", "))
+        }
+    }
 }
-/// parse_vec, but for float64
-fn parse_vec_f32(str: &str) -> (f32, f32) {
-    let parts: Vec<&str> = str[1..str.len()-1].split(",").collect();
-    let a: Vec<f32> = parts
-        .iter()
-        .map(|s| s.trim().parse::<f32>().unwrap())
+
+impl std::error::Error for AssetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AssetError::Io(err) => Some(err),
+            AssetError::Plist(err) => Some(err),
+            AssetError::Image(err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for AssetError {
+    fn from(err: std::io::Error) -> Self {
+        AssetError::Io(err)
+    }
+}
+
+impl From<plist::Error> for AssetError {
+    fn from(err: plist::Error) -> Self {
+        match err.into_io() {
+            Ok(err) => AssetError::Io(err),
+            Err(err) => AssetError::Plist(err)
+        }
+    }
+}
+
+impl From<image::ImageError> for AssetError {
+    fn from(err: image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(err) => AssetError::Io(err),
+            err => AssetError::Image(err)
+        }
+    }
+}
+
+/// Parses the comma separated numbers inside of `{...}`, ignoring any nested braces.
+fn parse_numbers<T: std::str::FromStr>(str: &str) -> Option<Vec<T>> {
+    let trimmed = str.trim();
+    if !trimmed.starts_with('{') || !trimmed.ends_with('}') {
+        return None;
+    }
+
+    return trimmed
+        .replace(['{', '}'], "")
+        .split(',')
+        .map(|s| s.trim().parse::<T>().ok())
         .collect();
+}
 
-    return (a[0], a[1])
+/// "{1,2}" -> `(1, 2)`
+fn parse_vec(str: &str) -> Result<(i32, i32), AssetError> {
+    match parse_numbers::<i32>(str).as_deref() {
+        Some(&[a, b]) => Ok((a, b)),
+        _ => Err(AssetError::InvalidVector(str.to_string()))
+    }
+}
+/// parse_vec, but for float64
+fn parse_vec_f32(str: &str) -> Result<(f32, f32), AssetError> {
+    match parse_numbers::<f32>(str).as_deref() {
+        Some(&[a, b]) => Ok((a, b)),
+        _ => Err(AssetError::InvalidVector(str.to_string()))
+    }
 }
+/// {left, top}, {width, height}
+type RectVecs = ((i32, i32), (i32, i32));
+
 /// `"{{1,2},{3,4}}"` -> `{{1, 2}, {3, 4}}`
-fn parse_rect_vecs(str: &str) -> ((i32, i32), (i32, i32)) {
-    let cleaned_str = str.replace("{", "").replace("}", "");
-    let parts: Vec<&str> = cleaned_str.split(",").collect();
-    let a: Vec<i32> = parts
-        .iter()
-        .map(|s| s.trim().parse::<i32>().unwrap())
-        .collect();
+fn parse_rect_vecs(str: &str) -> Result<RectVecs, AssetError> {
+    match parse_numbers::<i32>(str).as_deref() {
+        Some(&[a, b, c, d]) => Ok(((a, b), (c, d))),
+        _ => Err(AssetError::InvalidRect(str.to_string()))
+    }
+}
 
-    return ((a[0], a[1]), (a[2], a[3]))
+/// Gets a value out of a .plist dictionary, reporting which entry and key was missing otherwise.
+fn get_key<'a>(hash: &'a plist::Dictionary, sprite: &str, key: &str) -> Result<&'a plist::Value, AssetError> {
+    return hash.get(key).ok_or_else(|| AssetError::MissingKey { sprite: sprite.to_string(), key: key.to_string() });
+}
+/// get_key, but for values that must be a string
+fn get_string<'a>(hash: &'a plist::Dictionary, sprite: &str, key: &str) -> Result<&'a str, AssetError> {
+    return get_key(hash, sprite, key)?.as_string().ok_or_else(|| AssetError::MissingKey { sprite: sprite.to_string(), key: key.to_string() });
+}
+/// get_key, but for values that must be a dictionary
+fn get_dict<'a>(hash: &'a plist::Dictionary, sprite: &str, key: &str) -> Result<&'a plist::Dictionary, AssetError> {
+    return get_key(hash, sprite, key)?.as_dictionary().ok_or_else(|| AssetError::MissingKey { sprite: sprite.to_string(), key: key.to_string() });
+}
+/// Asserts that a .plist value is a dictionary
+fn as_dict<'a>(obj: &'a plist::Value, sprite: &str) -> Result<&'a plist::Dictionary, AssetError> {
+    return obj.as_dictionary().ok_or_else(|| AssetError::MissingKey { sprite: sprite.to_string(), key: "<root>".to_string() });
+}
+/// Parses a string value as a number
+fn parse_number<T: std::str::FromStr>(str: &str) -> Result<T, AssetError> {
+    return str.trim().parse::<T>().map_err(|_| AssetError::InvalidNumber(str.to_string()));
 }
 
 /// Represents a sprite along with its texture data in a spritesheet.
@@ -43,7 +153,7 @@ pub struct Sprite {
     /// Whenever rendering the sprite, offset it by this much.
     pub offset: (f32, f32),
     /// {left, top}, {width, height}. Controls the cropping.
-    pub rect: ((i32, i32), (i32, i32)),
+    pub rect: RectVecs,
     /// Whether the texture needs to be counter-rotated 90 degrees counter-clockwise.
     pub rotated: bool,
     /// Size of the sprite.
@@ -54,38 +164,19 @@ pub struct Sprite {
 
 impl Sprite {
     /// Shorthand for initializing a sprite with its .plist representation.
-    fn initialize(obj: plist::Value) -> Sprite {
-        let hash = obj.as_dictionary().expect("object must be a dict");
-
-        let hash_keys = vec!["spriteOffset", "spriteSize", "spriteSourceSize", "textureRect", "textureRotated"];
-
-        let isolated: Vec<(&&str, Option<&plist::Value>)> = hash_keys
-            .iter()
-            .map(|s| (s, hash.get(s)))
-            .collect();
-
-        let missing: Vec<&(&&str, Option<&plist::Value>)> = isolated
-            .iter()
-            .filter(|&&(_, value)| value.is_none())
-            .collect();
-
-        if !missing.is_empty() {
-            let missing_entries: Vec<&str> = missing.iter().map(|(&key, _)| key).collect();
-            panic!("missing entries: {:?}", missing_entries);
-        }
-
-        let isolated_hash: HashMap<String, plist::Value> = isolated
-            .iter()
-            .map(|&(key, value)| (key.to_string(), value.expect("value is none after checking").clone()))
-            .collect();
-
-        return Sprite {
-            offset: parse_vec_f32(isolated_hash.get("spriteOffset").expect("missing spriteOffset").as_string().expect("spriteOffset is not a string")),
-            rect: parse_rect_vecs(isolated_hash.get("textureRect").expect("missing textureRect").as_string().expect("textureRect is not a string")),
-            rotated: isolated_hash.get("textureRotated").unwrap_or(&plist::Value::from(false)).as_boolean().expect("textureRotated is not a boolean").clone(),
-            size: parse_vec(isolated_hash.get("spriteSize").expect("missing spriteSize").as_string().expect("spriteSize is not a string")),
-            source_size: parse_vec(isolated_hash.get("spriteSourceSize").expect("missing spriteSourceSize").as_string().expect("spriteSourceSize is not a string"))
-        }
+    fn initialize(name: &str, obj: &plist::Value) -> Result<Sprite, AssetError> {
+        let hash = as_dict(obj, name)?;
+
+        return Ok(Sprite {
+            offset: parse_vec_f32(get_string(hash, name, "spriteOffset")?)?,
+            rect: parse_rect_vecs(get_string(hash, name, "textureRect")?)?,
+            rotated: match hash.get("textureRotated") {
+                Some(value) => value.as_boolean().ok_or_else(|| AssetError::MissingKey { sprite: name.to_string(), key: "textureRotated".to_string() })?,
+                None => false
+            },
+            size: parse_vec(get_string(hash, name, "spriteSize")?)?,
+            source_size: parse_vec(get_string(hash, name, "spriteSourceSize")?)?
+        })
     }
 }
 
@@ -100,17 +191,27 @@ pub struct Spritesheet {
 
 impl Spritesheet {
     /// Shorthand for initializing a spritesheet with its .plist representation.
-    fn initialize(obj: plist::Value) -> Spritesheet {
-        let hash = obj.as_dictionary().expect("object must be a dict");
+    fn initialize(obj: plist::Value) -> Result<Spritesheet, AssetError> {
+        let hash = as_dict(&obj, "<root>")?;
 
-        let sprites = hash.get("frames").expect("object must have a `frames` object").as_dictionary().expect("`frames` must be a dict");
-        let metadata = hash.get("metadata").expect("object must have a `metadata` object").as_dictionary().expect("`metadata` must be a dict");
+        let sprites = get_dict(hash, "<root>", "frames")?;
+        let metadata = get_dict(hash, "<root>", "metadata")?;
 
-        return Spritesheet {
-            sprites: sprites.iter().map(|(key, value)| (key.clone(), Sprite::initialize(value.clone()))).collect(),
-            texture_file_name: metadata.get("textureFileName").expect("metadata must have a `textureFileName` object").as_string().expect("`textureFileName` must be a string").to_string(),
-            size: parse_vec(metadata.get("size").expect("metadata must have a `size` object").as_string().expect("`size` must be a string"))
-        }
+        return Ok(Spritesheet {
+            sprites: sprites.iter().map(|(key, value)| Ok((key.clone(), Sprite::initialize(key, value)?))).collect::<Result<_, AssetError>>()?,
+            texture_file_name: get_string(metadata, "metadata", "textureFileName")?.to_string(),
+            size: parse_vec(get_string(metadata, "metadata", "size")?)?
+        })
+    }
+
+    /// Parses a spritesheet out of a .plist file being read from `reader`.
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Spritesheet, AssetError> {
+        return Spritesheet::initialize(plist::from_reader(reader)?);
+    }
+
+    /// Parses a spritesheet out of the contents of a .plist file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Spritesheet, AssetError> {
+        return Spritesheet::initialize(plist::from_bytes(bytes)?);
     }
 }
 
@@ -121,17 +222,307 @@ pub struct LoadedSpritesheet {
     pub texture: DynamicImage
 }
 
-/// Loads the spritesheet and readies the associated image.
-pub fn load_spritesheet(path: &str) -> LoadedSpritesheet {
-    return LoadedSpritesheet {
-        spritesheet: Spritesheet::initialize(plist::from_file(path).expect("could not load plist")),
-        texture: image::open(path.replace(".plist", ".png")).expect("could not load texture")
+impl LoadedSpritesheet {
+    /// Pairs up a spritesheet with its texture, checking that every sprite actually lies within the texture.
+    pub fn new(spritesheet: Spritesheet, texture: DynamicImage) -> Result<LoadedSpritesheet, AssetError> {
+        // a broken texture pack shouldnt be able to make `get_sprite` read out of bounds
+        for (name, sprite) in spritesheet.sprites.iter() {
+            if sprite_bounds(sprite, &texture).is_none() {
+                return Err(AssetError::SpriteOutOfBounds { sprite: name.clone() });
+            }
+        }
+
+        return Ok(LoadedSpritesheet {
+            spritesheet,
+            texture
+        })
+    }
+
+    /// Loads a spritesheet from the contents of its .plist and .png files, eg. from an archive, an upload or `include_bytes!`.
+    pub fn from_parts(plist_bytes: &[u8], png_bytes: &[u8]) -> Result<LoadedSpritesheet, AssetError> {
+        return LoadedSpritesheet::new(Spritesheet::from_bytes(plist_bytes)?, image::load_from_memory(png_bytes)?);
+    }
+
+    /// Whether the spritesheet contains the icon with the given ID.
+    pub fn icon_exists(&self, gamemode: Gamemode, icon: i32) -> bool {
+        return self.spritesheet.sprites.contains_key(&gamemode.primary_sprite(icon));
+    }
+
+    /// The highest icon ID of a gamemode found in the spritesheet, or `None` if there are no icons of it at all.
+    pub fn max_icon_id(&self, gamemode: Gamemode) -> Option<i32> {
+        return self.spritesheet.sprites.keys()
+            .filter_map(|key| {
+                let id = key.strip_prefix(gamemode.prefix())?.split('_').next()?.parse::<i32>().ok()?;
+                (*key == gamemode.primary_sprite(id)).then_some(id)
+            })
+            .max();
     }
 }
 
-/// Represents the metadata of an animation frame's sprite
+/// Loads the spritesheet and readies the associated image, which must sit next to it with a `.png` extension.
+pub fn load_spritesheet(path: impl AsRef<Path>) -> Result<LoadedSpritesheet, AssetError> {
+    let path = path.as_ref();
+    return LoadedSpritesheet::from_parts(&std::fs::read(path)?, &std::fs::read(path.with_extension("png"))?);
+}
+
+/// The texture resolution of a spritesheet, as picked in the game's graphics settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureQuality {
+    /// `GJ_GameSheet02.plist`
+    Low,
+    /// `GJ_GameSheet02-hd.plist`
+    Medium,
+    /// `GJ_GameSheet02-uhd.plist`
+    High
+}
+
+impl TextureQuality {
+    /// The suffix the game appends to spritesheet filenames of this quality.
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            TextureQuality::Low => "",
+            TextureQuality::Medium => "-hd",
+            TextureQuality::High => "-uhd"
+        }
+    }
+
+    /// How many texture pixels make up a single in-game point at this quality.
+    pub fn scale_factor(&self) -> f32 {
+        match self {
+            TextureQuality::Low => 1.0,
+            TextureQuality::Medium => 2.0,
+            TextureQuality::High => 4.0
+        }
+    }
+
+    /// The next quality down, if there is one.
+    pub fn lower(&self) -> Option<TextureQuality> {
+        match self {
+            TextureQuality::Low => None,
+            TextureQuality::Medium => Some(TextureQuality::Low),
+            TextureQuality::High => Some(TextureQuality::Medium)
+        }
+    }
+
+    /// Guesses the quality of a spritesheet from its texture's filename, eg. `GJ_GameSheet02-uhd.png`.
+    pub fn from_file_name(file_name: &str) -> TextureQuality {
+        let stem = file_name.trim_end_matches(".png").trim_end_matches(".plist");
+        if stem.ends_with("-uhd") {
+            TextureQuality::High
+        } else if stem.ends_with("-hd") {
+            TextureQuality::Medium
+        } else {
+            TextureQuality::Low
+        }
+    }
+}
+
+/// Every spritesheet and animation description needed to render any icon.
+///
+/// Rendering only ever borrows this, so a single `IconAssets` can be loaded once and shared between threads (eg. behind an `Arc`).
+#[derive(Clone)]
+pub struct IconAssets {
+    /// `GJ_GameSheet02`
+    pub game_sheet_02: LoadedSpritesheet,
+    /// `GJ_GameSheetGlow`
+    pub game_sheet_glow: LoadedSpritesheet,
+    /// `Robot_AnimDesc2`
+    pub robot_animations: Animations,
+    /// `Spider_AnimDesc2`
+    pub spider_animations: Animations,
+    /// The quality of `game_sheet_02` and `game_sheet_glow`, which decides how animations and offsets are scaled.
+    pub quality: TextureQuality,
+    /// The `icons` directory 2.2 ships each icon's own spritesheet in, eg. `icons/player_123-uhd.plist`.
+    pub icons_dir: Option<PathBuf>,
+    /// Per-icon spritesheets loaded so far, by path, so clones with another quality don't mix them up. Only icons that have one are kept, so asking for made up icon IDs can't grow this.
+    icon_sheets: Arc<RwLock<HashMap<PathBuf, Arc<LoadedSpritesheet>>>>
+}
+
+/// The spritesheets an icon's sprites are found in, as resolved by [`IconAssets::icon_sheet`].
+pub enum IconSheet<'a> {
+    /// Pre-2.2 layout; the icon is part of `GJ_GameSheet02`, with its glow in `GJ_GameSheetGlow`.
+    Combined { sheet: &'a LoadedSpritesheet, glow: &'a LoadedSpritesheet },
+    /// 2.2 layout; the icon has a spritesheet of its own, glow included.
+    Separate(Arc<LoadedSpritesheet>)
+}
+
+impl IconSheet<'_> {
+    /// The spritesheet holding the icon's regular sprites.
+    pub fn sheet(&self) -> &LoadedSpritesheet {
+        match self {
+            IconSheet::Combined { sheet, .. } => sheet,
+            IconSheet::Separate(sheet) => sheet
+        }
+    }
+
+    /// The spritesheet holding the icon's glow sprites.
+    pub fn glow_sheet(&self) -> &LoadedSpritesheet {
+        match self {
+            IconSheet::Combined { glow, .. } => glow,
+            IconSheet::Separate(sheet) => sheet
+        }
+    }
+}
+
+impl IconAssets {
+    /// Bundles already loaded sheets together. The quality is guessed from the texture filename of `game_sheet_02`; use [`IconAssets::with_quality`] if your files are named differently.
+    pub fn new(game_sheet_02: LoadedSpritesheet, game_sheet_glow: LoadedSpritesheet, robot_animations: Animations, spider_animations: Animations) -> IconAssets {
+        let quality = TextureQuality::from_file_name(&game_sheet_02.spritesheet.texture_file_name);
+
+        return IconAssets {
+            game_sheet_02,
+            game_sheet_glow,
+            robot_animations,
+            spider_animations,
+            quality,
+            icons_dir: None,
+            icon_sheets: Arc::new(RwLock::new(HashMap::new()))
+        }
+    }
+
+    /// Sets the `icons` directory to look for per-icon spritesheets in.
+    pub fn with_icons_dir(mut self, icons_dir: impl AsRef<Path>) -> IconAssets {
+        self.icons_dir = Some(icons_dir.as_ref().to_path_buf());
+        self.icon_sheets = Arc::new(RwLock::new(HashMap::new()));
+        self
+    }
+
+    /// Path of the per-icon spritesheet for `basename` (eg. `player_123`), if there's an `icons` directory to look in.
+    fn icon_sheet_path(&self, basename: &str) -> Option<PathBuf> {
+        return self.icons_dir.as_ref().map(|dir| dir.join(format!("{}{}.plist", basename, self.quality.suffix())));
+    }
+
+    /// Finds the spritesheets holding the icon with the given basename (eg. `player_123`).
+    ///
+    /// The icon's own spritesheet in `icons_dir` is loaded the first time it's asked for and kept around afterwards. Icons without one fall back to `game_sheet_02` and `game_sheet_glow`, checking for the file again every time.
+    pub fn icon_sheet(&self, basename: &str) -> Result<IconSheet<'_>, AssetError> {
+        let combined = IconSheet::Combined { sheet: &self.game_sheet_02, glow: &self.game_sheet_glow };
+
+        let path = match self.icon_sheet_path(basename) {
+            Some(path) => path,
+            None => return Ok(combined)
+        };
+
+        // a poisoned lock only means another render panicked mid-insert, the map itself is fine
+        if let Some(cached) = self.icon_sheets.read().unwrap_or_else(|err| err.into_inner()).get(&path) {
+            return Ok(IconSheet::Separate(cached.clone()));
+        }
+
+        if !path.is_file() {
+            return Ok(combined);
+        }
+
+        let loaded = Arc::new(load_spritesheet(&path)?);
+        self.icon_sheets.write().unwrap_or_else(|err| err.into_inner()).insert(path, loaded.clone());
+
+        return Ok(IconSheet::Separate(loaded));
+    }
+
+    /// The animations of an [animated](Gamemode::is_animated) gamemode.
+    pub fn animations(&self, gamemode: Gamemode) -> Option<&Animations> {
+        match gamemode {
+            Gamemode::Robot => Some(&self.robot_animations),
+            Gamemode::Spider => Some(&self.spider_animations),
+            _ => None
+        }
+    }
+
+    /// The name and frame count of every animation a gamemode can be rendered in, eg. `("run", 4)`. Empty for gamemodes that aren't animated.
+    pub fn list_animations(&self, gamemode: Gamemode) -> Vec<(String, usize)> {
+        return self.animations(gamemode).map(list_animations).unwrap_or_default();
+    }
+
+    /// Whether the icon with the given ID exists, either as its own spritesheet or in `game_sheet_02`.
+    pub fn icon_exists(&self, gamemode: Gamemode, icon: i32) -> bool {
+        let has_own_sheet = self.icon_sheet_path(&gamemode.basename(icon)).is_some_and(|path| path.is_file());
+
+        return has_own_sheet || self.game_sheet_02.icon_exists(gamemode, icon);
+    }
+
+    /// The highest icon ID of a gamemode, counting both per-icon spritesheets and `game_sheet_02`.
+    pub fn max_icon_id(&self, gamemode: Gamemode) -> Option<i32> {
+        let combined = self.game_sheet_02.max_icon_id(gamemode);
+
+        let Some(icons_dir) = self.icons_dir.as_ref() else {
+            return combined;
+        };
+        let Ok(entries) = std::fs::read_dir(icons_dir) else {
+            return combined;
+        };
+
+        let suffix = format!("{}.plist", self.quality.suffix());
+        let separate = entries
+            .filter_map(|entry| {
+                let file_name = entry.ok()?.file_name().into_string().ok()?;
+                let id = file_name.strip_prefix(gamemode.prefix())?.strip_suffix(&suffix)?.parse::<i32>().ok()?;
+                (file_name == format!("{}{}", gamemode.basename(id), suffix)).then_some(id)
+            })
+            .max();
+
+        return combined.max(separate);
+    }
+
+    /// Overrides the quality the spritesheets are treated as.
+    pub fn with_quality(mut self, quality: TextureQuality) -> IconAssets {
+        self.quality = quality;
+        self
+    }
+
+    /// Loads `GJ_GameSheet02`, `GJ_GameSheetGlow`, `Robot_AnimDesc2` and `Spider_AnimDesc2` out of a Geometry Dash `Resources` directory. If there is an `icons` directory inside of it, per-icon spritesheets are picked up from there as they're needed.
+    ///
+    /// Spritesheets of `quality` are preferred, falling back to lower qualities if they aren't installed. Every file is checked for before anything is loaded; if no quality is complete, a [`AssetError::MissingFiles`] lists all of the files missing for the preferred one.
+    pub fn load(resources: impl AsRef<Path>, quality: TextureQuality) -> Result<IconAssets, AssetError> {
+        let resources = resources.as_ref();
+
+        let robot_path = resources.join("Robot_AnimDesc2.plist");
+        let spider_path = resources.join("Spider_AnimDesc2.plist");
+
+        let mut preferred_missing: Option<Vec<PathBuf>> = None;
+        let mut current = Some(quality);
+
+        while let Some(quality) = current {
+            let spritesheet_path = |name: &str| resources.join(format!("{}{}.plist", name, quality.suffix()));
+            let game_sheet_02_path = spritesheet_path("GJ_GameSheet02");
+            let game_sheet_glow_path = spritesheet_path("GJ_GameSheetGlow");
+
+            let missing: Vec<PathBuf> = [
+                game_sheet_02_path.clone(),
+                game_sheet_02_path.with_extension("png"),
+                game_sheet_glow_path.clone(),
+                game_sheet_glow_path.with_extension("png"),
+                robot_path.clone(),
+                spider_path.clone()
+            ].into_iter().filter(|path| !path.is_file()).collect();
+
+            if missing.is_empty() {
+                let mut assets = IconAssets::new(
+                    load_spritesheet(game_sheet_02_path)?,
+                    load_spritesheet(game_sheet_glow_path)?,
+                    load_animations(&robot_path)?,
+                    load_animations(&spider_path)?
+                ).with_quality(quality);
+
+                let icons_dir = resources.join("icons");
+                if icons_dir.is_dir() {
+                    assets = assets.with_icons_dir(icons_dir);
+                }
+
+                return Ok(assets);
+            }
+
+            preferred_missing.get_or_insert(missing);
+            current = quality.lower();
+        }
+
+        return Err(AssetError::MissingFiles(preferred_missing.unwrap_or_default()));
+    }
+}
+
+/// A single sprite of an animation frame, eg. a robot's leg
 #[derive(Clone, Debug)]
-pub struct AnimationSprite {
+pub struct AnimationPart {
+    /// The part's key in the frame, eg. `sprite_3`
+    pub name: String,
     pub texture: String,
     pub position: (f32, f32),
     pub scale: (f32, f32),
@@ -140,91 +531,125 @@ pub struct AnimationSprite {
     pub z: i32
 }
 
-impl AnimationSprite {
-    fn initialize(obj: plist::Value) -> AnimationSprite {
-        let hash = obj.as_dictionary().expect("object must be a dict");
+impl AnimationPart {
+    fn initialize(frame: &str, name: &str, obj: &plist::Value) -> Result<AnimationPart, AssetError> {
+        let path = format!("{}/{}", frame, name);
+        let hash = as_dict(obj, &path)?;
+
+        return Ok(AnimationPart {
+            name: name.to_string(),
+            texture: get_string(hash, &path, "texture")?.to_string(),
+            position: parse_vec_f32(get_string(hash, &path, "position")?)?,
+            scale: parse_vec_f32(get_string(hash, &path, "scale")?)?,
+            rotation: parse_number::<f64>(get_string(hash, &path, "rotation")?)?,
+            flipped: {
+                let flipped_numbers = parse_vec(get_string(hash, &path, "flipped")?)?;
+                (flipped_numbers.0 > 0, flipped_numbers.1 > 0)
+            },
+            z: parse_number::<i32>(get_string(hash, &path, "zValue")?)?
+        })
+    }
+}
 
-        let hash_keys = vec!["texture", "position", "scale", "rotation", "flipped", "zValue"];
+/// A single pose of an animation.
+#[derive(Clone, Debug)]
+pub struct AnimationFrame {
+    /// The frame number, starting at 1 like in the game's files
+    pub index: usize,
+    /// Every part of the pose, in the order the game lists them in
+    pub parts: Vec<AnimationPart>
+}
 
-        let isolated: Vec<(&&str, Option<&plist::Value>)> = hash_keys
-            .iter()
-            .map(|s| (s, hash.get(s)))
-            .collect();
+/// A robot or spider animation, eg. `run`.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub name: String,
+    /// Every frame of the animation, sorted by index
+    pub frames: Vec<AnimationFrame>
+}
 
-        let missing: Vec<&(&&str, Option<&plist::Value>)> = isolated
-            .iter()
-            .filter(|&&(_, value)| value.is_none())
-            .collect();
+impl Animation {
+    /// The frame with the given index, starting at 1.
+    pub fn frame(&self, index: usize) -> Option<&AnimationFrame> {
+        return self.frames.iter().find(|frame| frame.index == index);
+    }
+}
 
-        if !missing.is_empty() {
-            let missing_entries: Vec<&str> = missing.iter().map(|(&key, _)| key).collect();
-            panic!("missing entries: {:?}", missing_entries);
-        }
+/// Every animation of a gamemode, keyed by name
+pub type Animations = HashMap<String, Animation>;
 
-        let isolated_hash: HashMap<String, plist::Value> = isolated
-            .iter()
-            .map(|&(key, value)| (key.to_string(), value.expect("value is none after checking").clone()))
-            .collect();
-
-        return AnimationSprite {
-            texture: isolated_hash.get("texture").expect("missing texture").as_string().expect("texture is not a string").to_string(),
-            position: parse_vec_f32(isolated_hash.get("position").expect("missing position").as_string().expect("position is not a string")),
-            scale: parse_vec_f32(isolated_hash.get("scale").expect("missing scale").as_string().expect("scale is not a string")),
-            rotation: isolated_hash.get("rotation").expect("missing rotation").as_string().expect("rotation is not a string").parse::<f64>().expect("couldnt parse rotation as f64"),
-            flipped: {
-                let flipped_numbers = parse_vec(isolated_hash.get("flipped").expect("missing flipped").as_string().expect("flipped is not a string"));
-                (flipped_numbers.0 > 0, flipped_numbers.1 > 0)
-            },
-            z: isolated_hash.get("zValue").expect("missing zValue").as_string().expect("zValue is not a string").parse::<i32>().expect("couldnt parse zValue as i32")
-        }
-    }
+/// Splits the key of an animation frame into the animation's name and the frame number, eg. `Robot_fall_loop_002.png` -> `("fall_loop", 2)`.
+pub fn parse_animation_key(key: &str) -> Option<(&str, usize)> {
+    let (_, rest) = key.strip_suffix(".png")?.split_once('_')?;
+    let (name, frame) = rest.rsplit_once('_')?;
+    return Some((name, frame.parse::<usize>().ok()?));
 }
 
-pub type Animations = HashMap<String, Vec<AnimationSprite>>;
+/// Every animation's name along with how many frames it has, sorted by name.
+pub fn list_animations(animations: &Animations) -> Vec<(String, usize)> {
+    let mut list: Vec<(String, usize)> = animations.values().map(|animation| (animation.name.clone(), animation.frames.len())).collect();
+    list.sort();
+    return list;
+}
 
-pub fn load_animations(path: &str) -> Animations {
-    let loaded_plist: plist::Value = plist::from_file(path).expect("could not load plist");
-    let animations = loaded_plist.as_dictionary().expect("object must be a dict").get("animationContainer").expect("key `animationContainer` doesnt exist").as_dictionary().expect("`animationContainer` must be a dict");
+/// Loads every animation out of an `*_AnimDesc2.plist` file.
+pub fn load_animations(path: impl AsRef<Path>) -> Result<Animations, AssetError> {
+    return load_animations_from_reader(BufReader::new(std::fs::File::open(path)?));
+}
+
+/// Loads every animation out of an `*_AnimDesc2.plist` file being read from `reader`.
+pub fn load_animations_from_reader<R: Read + Seek>(reader: R) -> Result<Animations, AssetError> {
+    let loaded_plist: plist::Value = plist::from_reader(reader)?;
+    let animations = get_dict(as_dict(&loaded_plist, "<root>")?, "<root>", "animationContainer")?;
     let mut parsed_animations: Animations = HashMap::new();
     for (k, v) in animations.iter() {
-        parsed_animations.insert(k.clone(), vec![] as Vec<AnimationSprite>);
-        parsed_animations.get_mut(k.as_str()).expect("this should exist..")
-            .extend(v.as_dictionary().expect("animation must be a dict")
-            .iter().map(|(_, v)| AnimationSprite::initialize(v.clone())));
+        let (name, index) = parse_animation_key(k).ok_or_else(|| AssetError::InvalidAnimationKey(k.clone()))?;
+        let parts = as_dict(v, k)?
+            .iter()
+            .map(|(part, v)| AnimationPart::initialize(k, part, v))
+            .collect::<Result<Vec<AnimationPart>, AssetError>>()?;
+
+        parsed_animations
+            .entry(name.to_string())
+            .or_insert_with(|| Animation { name: name.to_string(), frames: Vec::new() })
+            .frames
+            .push(AnimationFrame { index, parts });
     }
-    return parsed_animations;
+    for animation in parsed_animations.values_mut() {
+        animation.frames.sort_by_key(|frame| frame.index);
+    }
+    return Ok(parsed_animations);
 }
 
-/// Trims out a sprite from an image according to a .plist spritesheet.
-pub fn get_sprite(spritesheet: Spritesheet, img: &DynamicImage, key: String) -> Option<(DynamicImage, Sprite)> {
-    let sprite = spritesheet.sprites.get(&key);
-
-    if sprite.is_none() {
+/// Where a sprite lies in its texture as left, top, width and height, or `None` if any of it is outside of the texture.
+fn sprite_bounds(sprite: &Sprite, texture: &DynamicImage) -> Option<(u32, u32, u32, u32)> {
+    let ((left, top), (mut width, mut height)) = sprite.rect;
+    if sprite.rotated {
+        (width, height) = (height, width);
+    }
+    // widened so a huge rect can't overflow instead of failing the check
+    if left < 0 || top < 0 || width < 0 || height < 0 || left as i64 + width as i64 > texture.width() as i64 || top as i64 + height as i64 > texture.height() as i64 {
         return None;
     }
+    return Some((left as u32, top as u32, width as u32, height as u32));
+}
 
-    if let Some(sprite) = sprite {
-        let rect = sprite.rect;
-        
-        let (mut left, mut top, mut width, mut height) = (rect.0.0, rect.0.1, rect.1.0, rect.1.1);
-        if sprite.rotated {
-            (left, top, width, height) = (left, top, height, width);
-        }
-        
-        let mut canvas: DynamicImage = image::DynamicImage::ImageRgba8(img.view(left as u32, top as u32, width as u32, height as u32).to_image());
+/// Trims out a sprite from an image according to a .plist spritesheet, or `None` if it isn't in the spritesheet or lies outside of the image.
+pub fn get_sprite(spritesheet: &Spritesheet, img: &DynamicImage, key: &str) -> Option<(DynamicImage, Sprite)> {
+    let sprite = spritesheet.sprites.get(key)?;
+    let (left, top, width, height) = sprite_bounds(sprite, img)?;
 
-        if sprite.rotated {
-            canvas = canvas.rotate270();
-        }
+    let mut canvas: DynamicImage = image::DynamicImage::ImageRgba8(img.view(left, top, width, height).to_image());
 
-        return Some((canvas, sprite.clone()));
+    if sprite.rotated {
+        canvas = canvas.rotate270();
     }
 
-    unreachable!("The sprite should have been found in the spritesheet or not found at all")
+    return Some((canvas, *sprite));
 }
 
 /// Trims out a sprite from an image according to a LoadedSpritesheet object.
-pub fn get_sprite_from_loaded(spritesheet: &LoadedSpritesheet, key: String) -> Option<(DynamicImage, Sprite)> {
-    let sprite = get_sprite(spritesheet.spritesheet.clone(), &spritesheet.texture, key);
+pub fn get_sprite_from_loaded(spritesheet: &LoadedSpritesheet, key: &str) -> Option<(DynamicImage, Sprite)> {
+    let sprite = get_sprite(&spritesheet.spritesheet, &spritesheet.texture, key);
     return sprite;
 }
\ No newline at end of file
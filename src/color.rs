@@ -0,0 +1,155 @@
+use crate::constants::COLORS;
+
+/// An RGB color stored as floats from 0 to 1, what icon layers get tinted with.
+///
+/// Parses from hex (`#ff7d7d`, `ff7d7d` or `#f77`), `rgb(255, 125, 125)` or the name of one of the game's palette colors (eg. `orange`), and displays as lowercase hex. With the `serde` feature it (de)serializes as a string the same way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32
+}
+
+/// Names [`Color`] parses, and the game's color ID they stand for
+const NAMED_COLORS: &[(&str, usize)] = &[
+    ("white", 12),
+    ("black", 15),
+    ("gray", 17),
+    ("grey", 17),
+    ("red", 9),
+    ("orange", 10),
+    ("yellow", 11),
+    ("green", 1),
+    ("cyan", 3),
+    ("blue", 5),
+    ("purple", 6),
+    ("magenta", 7)
+];
+
+impl Color {
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+
+    /// A color from channels from 0 to 1.
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Color {
+        return Color { r, g, b };
+    }
+
+    /// A color from channels from 0 to 255.
+    pub const fn rgb8(r: u8, g: u8, b: u8) -> Color {
+        return Color::rgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    }
+
+    /// The color's channels from 0 to 255, rounded.
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        return self.to_array().map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    }
+
+    /// The color's channels from 0 to 1.
+    pub fn to_array(&self) -> [f32; 3] {
+        return [self.r, self.g, self.b];
+    }
+
+    pub fn is_black(&self) -> bool {
+        return self.to_array() == [0.0, 0.0, 0.0];
+    }
+
+    /// Parses a hex color like `#ff7d7d`, `ff7d7d` or `#f77`.
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let digits = hex.trim().trim_start_matches('#');
+        if !digits.is_ascii() {
+            return None;
+        }
+
+        let channels = match digits.len() {
+            3 => digits.chars().map(|c| u8::from_str_radix(&c.to_string(), 16).map(|c| c * 17)).collect::<Result<Vec<u8>, _>>(),
+            6 => (0..6).step_by(2).map(|i| u8::from_str_radix(&digits[i..i + 2], 16)).collect::<Result<Vec<u8>, _>>(),
+            _ => return None
+        }.ok()?;
+
+        return Some(Color::rgb8(channels[0], channels[1], channels[2]));
+    }
+
+    /// Looks up one of the game's palette colors by name, eg. `orange`, ignoring case.
+    pub fn named(name: &str) -> Option<Color> {
+        let name = name.trim().to_lowercase();
+        return NAMED_COLORS.iter().find(|(named, _)| *named == name).map(|(_, index)| COLORS[*index]);
+    }
+}
+
+/// Parses `rgb(255, 125, 125)`
+fn parse_rgb_function(str: &str) -> Option<Color> {
+    let str = str.trim().to_lowercase();
+    let channels = str.strip_prefix("rgb(")?.strip_suffix(')')?
+        .split(',')
+        .map(|c| c.trim().parse::<u8>())
+        .collect::<Result<Vec<u8>, _>>().ok()?;
+
+    if channels.len() != 3 {
+        return None;
+    }
+    return Some(Color::rgb8(channels[0], channels[1], channels[2]));
+}
+
+/// Returned when a string isn't a color [`Color`] knows how to parse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseColorError(pub String);
+
+impl std::fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown color `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+impl std::str::FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        return Color::named(str)
+            .or_else(|| parse_rgb_function(str))
+            .or_else(|| Color::from_hex(str))
+            .ok_or_else(|| ParseColorError(str.to_string()));
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [r, g, b] = self.to_rgb8();
+        write!(f, "#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+impl From<[f32; 3]> for Color {
+    fn from(rgb: [f32; 3]) -> Self {
+        Color::rgb(rgb[0], rgb[1], rgb[2])
+    }
+}
+
+impl From<[u8; 3]> for Color {
+    fn from(rgb: [u8; 3]) -> Self {
+        Color::rgb8(rgb[0], rgb[1], rgb[2])
+    }
+}
+
+impl From<Color> for [f32; 3] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let str = String::deserialize(deserializer)?;
+        return str.parse().map_err(serde::de::Error::custom);
+    }
+}
@@ -1,66 +1,327 @@
-use std::{collections::HashMap, sync::LazyLock};
-
-use maplit::hashmap;
-
-/// Colors used in the game stored as floats from 0 to 1.
-pub const COLORS: &'static [[f32; 3]] = &[
-    [125.0 / 255.0, 255.0 / 255.0, 0.0 / 255.0],
-    [0.0 / 255.0, 255.0 / 255.0, 0.0 / 255.0],
-    [0.0 / 255.0, 255.0 / 255.0, 125.0 / 255.0],
-    [0.0 / 255.0, 255.0 / 255.0, 255.0 / 255.0],
-    [0.0 / 255.0, 125.0 / 255.0, 255.0 / 255.0],
-    [0.0 / 255.0, 0.0 / 255.0, 255.0 / 255.0],
-    [125.0 / 255.0, 0.0 / 255.0, 255.0 / 255.0],
-    [255.0 / 255.0, 0.0 / 255.0, 255.0 / 255.0],
-    [255.0 / 255.0, 0.0 / 255.0, 125.0 / 255.0],
-    [255.0 / 255.0, 0.0 / 255.0, 0.0 / 255.0],
-    [255.0 / 255.0, 125.0 / 255.0, 0.0 / 255.0],
-    [255.0 / 255.0, 255.0 / 255.0, 0.0 / 255.0],
-    [255.0 / 255.0, 255.0 / 255.0, 255.0 / 255.0],
-    [185.0 / 255.0, 0.0 / 255.0, 255.0 / 255.0],
-    [255.0 / 255.0, 185.0 / 255.0, 0.0 / 255.0],
-    [0.0 / 255.0, 0.0 / 255.0, 0.0 / 255.0],
-    [0.0 / 255.0, 200.0 / 255.0, 255.0 / 255.0],
-    [175.0 / 255.0, 175.0 / 255.0, 175.0 / 255.0],
-    [90.0 / 255.0, 90.0 / 255.0, 90.0 / 255.0],
-    [255.0 / 255.0, 125.0 / 255.0, 125.0 / 255.0],
-    [0.0 / 255.0, 175.0 / 255.0, 75.0 / 255.0],
-    [0.0 / 255.0, 125.0 / 255.0, 125.0 / 255.0],
-    [0.0 / 255.0, 75.0 / 255.0, 175.0 / 255.0],
-    [75.0 / 255.0, 0.0 / 255.0, 175.0 / 255.0],
-    [125.0 / 255.0, 0.0 / 255.0, 125.0 / 255.0],
-    [175.0 / 255.0, 0.0 / 255.0, 75.0 / 255.0],
-    [175.0 / 255.0, 75.0 / 255.0, 0.0 / 255.0],
-    [125.0 / 255.0, 125.0 / 255.0, 0.0 / 255.0],
-    [75.0 / 255.0, 175.0 / 255.0, 0.0 / 255.0],
-    [255.0 / 255.0, 75.0 / 255.0, 0.0 / 255.0],
-    [150.0 / 255.0, 50.0 / 255.0, 0.0 / 255.0],
-    [150.0 / 255.0, 100.0 / 255.0, 0.0 / 255.0],
-    [100.0 / 255.0, 150.0 / 255.0, 0.0 / 255.0],
-    [0.0 / 255.0, 150.0 / 255.0, 100.0 / 255.0],
-    [0.0 / 255.0, 100.0 / 255.0, 150.0 / 255.0],
-    [100.0 / 255.0, 0.0 / 255.0, 150.0 / 255.0],
-    [150.0 / 255.0, 0.0 / 255.0, 100.0 / 255.0],
-    [150.0 / 255.0, 0.0 / 255.0, 0.0 / 255.0],
-    [0.0 / 255.0, 150.0 / 255.0, 0.0 / 255.0],
-    [0.0 / 255.0, 0.0 / 255.0, 150.0 / 255.0],
-    [125.0 / 255.0, 255.0 / 255.0, 175.0 / 255.0],
-    [125.0 / 255.0, 125.0 / 255.0, 255.0 / 255.0]
+use crate::color::{Color, ParseColorError};
+
+/// Colors used in the game, indexed by the game's color ID.
+///
+/// Only has the colors up to 2.1 (IDs 0 to 41); the ones 2.2 added (up to [`GAME_COLOR_COUNT`]) aren't here yet.
+pub const COLORS: &[Color] = &[
+    Color::rgb8(125, 255, 0),
+    Color::rgb8(0, 255, 0),
+    Color::rgb8(0, 255, 125),
+    Color::rgb8(0, 255, 255),
+    Color::rgb8(0, 125, 255),
+    Color::rgb8(0, 0, 255),
+    Color::rgb8(125, 0, 255),
+    Color::rgb8(255, 0, 255),
+    Color::rgb8(255, 0, 125),
+    Color::rgb8(255, 0, 0),
+    Color::rgb8(255, 125, 0),
+    Color::rgb8(255, 255, 0),
+    Color::rgb8(255, 255, 255),
+    Color::rgb8(185, 0, 255),
+    Color::rgb8(255, 185, 0),
+    Color::rgb8(0, 0, 0),
+    Color::rgb8(0, 200, 255),
+    Color::rgb8(175, 175, 175),
+    Color::rgb8(90, 90, 90),
+    Color::rgb8(255, 125, 125),
+    Color::rgb8(0, 175, 75),
+    Color::rgb8(0, 125, 125),
+    Color::rgb8(0, 75, 175),
+    Color::rgb8(75, 0, 175),
+    Color::rgb8(125, 0, 125),
+    Color::rgb8(175, 0, 75),
+    Color::rgb8(175, 75, 0),
+    Color::rgb8(125, 125, 0),
+    Color::rgb8(75, 175, 0),
+    Color::rgb8(255, 75, 0),
+    Color::rgb8(150, 50, 0),
+    Color::rgb8(150, 100, 0),
+    Color::rgb8(100, 150, 0),
+    Color::rgb8(0, 150, 100),
+    Color::rgb8(0, 100, 150),
+    Color::rgb8(100, 0, 150),
+    Color::rgb8(150, 0, 100),
+    Color::rgb8(150, 0, 0),
+    Color::rgb8(0, 150, 0),
+    Color::rgb8(0, 0, 150),
+    Color::rgb8(125, 255, 175),
+    Color::rgb8(125, 125, 255)
 ];
 
-/// `zany` = uses 2.0 gamemode render system w/ multiple moving parts
-pub struct Gamemode {
-    pub prefix: String,
-    pub zany: bool
-}
-
-/// Gamemodes used in the game. `zany` = uses 2.0 gamemode render system w/ multiple moving parts
-pub static GAMEMODES: LazyLock<HashMap<&str, Gamemode>> = LazyLock::new(|| { hashmap! {
-    "cube" => Gamemode { prefix: "player_".to_string(), zany: false },
-    "ship" => Gamemode { prefix: "ship_".to_string(), zany: false },
-    "ball" => Gamemode { prefix: "player_ball_".to_string(), zany: false },
-    "ufo" => Gamemode { prefix: "bird_".to_string(), zany: false },
-    "wave" => Gamemode { prefix: "dart_".to_string(), zany: false },
-    "robot" => Gamemode { prefix: "robot_".to_string(), zany: true },
-    "spider" => Gamemode { prefix: "spider_".to_string(), zany: true }
-}});
+/// How many color IDs the game has as of 2.2, including the ones missing from [`COLORS`].
+pub const GAME_COLOR_COUNT: usize = 107;
+
+/// A color, either picked from the game's palette or given directly.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColorSpec {
+    /// The game's color ID, as its servers and save files store it, looked up in [`COLORS`]
+    Index(usize),
+    /// Channels from 0 to 255
+    Rgb8([u8; 3]),
+    /// Channels from 0 to 1
+    Rgb([f32; 3]),
+    Color(Color),
+    /// Anything [`Color`] parses, eg. `#ff7d7d`, `rgb(255, 125, 125)` or `orange`
+    Text(String)
+}
+
+/// Returned when a [`ColorSpec`] doesn't describe a color.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ColorError {
+    /// The palette index is past the end of [`COLORS`]
+    IndexOutOfRange(usize),
+    /// The game has this color ID, but [`COLORS`] doesn't have its color yet
+    MissingColor(usize),
+    Invalid(ParseColorError)
+}
+
+impl std::fmt::Display for ColorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorError::IndexOutOfRange(index) => write!(f, "color index {} is out of range (the palette has {} colors)", index, COLORS.len()),
+            ColorError::MissingColor(index) => write!(f, "color ID {} is from 2.2, whose colors aren't supported yet", index),
+            ColorError::Invalid(err) => write!(f, "{}", err)
+        }
+    }
+}
+
+impl std::error::Error for ColorError {}
+
+impl From<ParseColorError> for ColorError {
+    fn from(err: ParseColorError) -> Self {
+        ColorError::Invalid(err)
+    }
+}
+
+impl ColorSpec {
+    /// The color this describes.
+    pub fn resolve(&self) -> Result<Color, ColorError> {
+        match self {
+            ColorSpec::Index(index) => match COLORS.get(*index) {
+                Some(color) => Ok(*color),
+                None if *index < GAME_COLOR_COUNT => Err(ColorError::MissingColor(*index)),
+                None => Err(ColorError::IndexOutOfRange(*index))
+            },
+            ColorSpec::Rgb8(rgb) => Ok(Color::from(*rgb)),
+            ColorSpec::Rgb(rgb) => Ok(Color::from(*rgb)),
+            ColorSpec::Color(color) => Ok(*color),
+            ColorSpec::Text(str) => str.parse().map_err(ColorError::Invalid)
+        }
+    }
+}
+
+impl From<[f32; 3]> for ColorSpec {
+    fn from(rgb: [f32; 3]) -> Self {
+        ColorSpec::Rgb(rgb)
+    }
+}
+
+impl From<[u8; 3]> for ColorSpec {
+    fn from(rgb: [u8; 3]) -> Self {
+        ColorSpec::Rgb8(rgb)
+    }
+}
+
+impl From<Color> for ColorSpec {
+    fn from(color: Color) -> Self {
+        ColorSpec::Color(color)
+    }
+}
+
+impl From<&str> for ColorSpec {
+    fn from(str: &str) -> Self {
+        ColorSpec::Text(str.to_string())
+    }
+}
+
+/// Gamemodes used in the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Gamemode {
+    Cube,
+    Ship,
+    Ball,
+    Ufo,
+    Wave,
+    Robot,
+    Spider,
+    Swing,
+    Jetpack
+}
+
+/// What a sprite layer of an icon gets tinted with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerColor {
+    Primary,
+    Secondary,
+    Glow,
+    /// Drawn as-is
+    Untinted
+}
+
+/// A sprite layer of an icon, eg. `_2` for `ship_44_2_001.png`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layer {
+    /// What goes between the basename and `_001.png`
+    pub suffix: &'static str,
+    pub color: LayerColor
+}
+
+const fn layer(suffix: &'static str, color: LayerColor) -> Layer {
+    Layer { suffix, color }
+}
+
+/// Glow, secondary, uncolored detail, primary, uncolored extra
+const DEFAULT_LAYERS: &[Layer] = &[
+    layer("_glow", LayerColor::Glow),
+    layer("_2", LayerColor::Secondary),
+    layer("_3", LayerColor::Untinted),
+    layer("", LayerColor::Primary),
+    layer("_extra", LayerColor::Untinted)
+];
+
+/// Same as the default, except the jetpack's extra sprite sits behind its body rather than on top of it
+const JETPACK_LAYERS: &[Layer] = &[
+    layer("_glow", LayerColor::Glow),
+    layer("_extra", LayerColor::Untinted),
+    layer("_2", LayerColor::Secondary),
+    layer("_3", LayerColor::Untinted),
+    layer("", LayerColor::Primary)
+];
+
+/// Where the player's cube sits inside of a vehicle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RiderPlacement {
+    /// Offset of the cube's center from the vehicle's center, in in-game points (y up)
+    pub offset: (f32, f32),
+    /// How much the cube is scaled down by
+    pub scale: f32,
+    /// Whether the cube is drawn over the vehicle rather than sitting inside of it
+    pub in_front: bool
+}
+
+impl Gamemode {
+    /// Every gamemode, in the order they're shown in the game's garage.
+    pub const ALL: [Gamemode; 9] = [
+        Gamemode::Cube,
+        Gamemode::Ship,
+        Gamemode::Ball,
+        Gamemode::Ufo,
+        Gamemode::Wave,
+        Gamemode::Robot,
+        Gamemode::Spider,
+        Gamemode::Swing,
+        Gamemode::Jetpack
+    ];
+
+    /// What every sprite name of this gamemode starts with, eg. `ship_`
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Gamemode::Cube => "player_",
+            Gamemode::Ship => "ship_",
+            Gamemode::Ball => "player_ball_",
+            Gamemode::Ufo => "bird_",
+            Gamemode::Wave => "dart_",
+            Gamemode::Robot => "robot_",
+            Gamemode::Spider => "spider_",
+            Gamemode::Swing => "swing_",
+            Gamemode::Jetpack => "jetpack_"
+        }
+    }
+
+    /// Iterates over every gamemode.
+    pub fn iter() -> impl Iterator<Item = Gamemode> {
+        Gamemode::ALL.into_iter()
+    }
+
+    /// The name of this gamemode, as accepted by `from_str`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gamemode::Cube => "cube",
+            Gamemode::Ship => "ship",
+            Gamemode::Ball => "ball",
+            Gamemode::Ufo => "ufo",
+            Gamemode::Wave => "wave",
+            Gamemode::Robot => "robot",
+            Gamemode::Spider => "spider",
+            Gamemode::Swing => "swing",
+            Gamemode::Jetpack => "jetpack"
+        }
+    }
+
+    /// Whether this gamemode is animated, ie. uses the 2.0 gamemode render system w/ multiple moving parts (robots and spiders)
+    pub fn is_animated(&self) -> bool {
+        matches!(self, Gamemode::Robot | Gamemode::Spider)
+    }
+
+    /// The sprite layers of a non-zany icon of this gamemode, from back to front.
+    pub fn layers(&self) -> &'static [Layer] {
+        match self {
+            Gamemode::Jetpack => JETPACK_LAYERS,
+            _ => DEFAULT_LAYERS
+        }
+    }
+
+    /// Where the cube is drawn in this gamemode, or `None` if it isn't a vehicle the cube rides in.
+    ///
+    /// These are approximations. The game doesn't ship them as data, so they were matched by eye against its garage preview and may be off by a point or so.
+    pub fn rider_placement(&self) -> Option<RiderPlacement> {
+        match self {
+            Gamemode::Ship => Some(RiderPlacement { offset: (0.0, 10.0), scale: 0.55, in_front: false }),
+            Gamemode::Ufo => Some(RiderPlacement { offset: (0.0, 5.0), scale: 0.55, in_front: false }),
+            Gamemode::Jetpack => Some(RiderPlacement { offset: (6.0, 4.0), scale: 0.6, in_front: true }),
+            _ => None
+        }
+    }
+
+    /// The sprite basename of an icon, eg. `ship_44`
+    pub fn basename(&self, icon: i32) -> String {
+        format!("{}{:02}", self.prefix(), icon)
+    }
+
+    /// The sprite every icon of this gamemode must have, eg. `ship_44_001.png` or `robot_05_01_001.png`
+    pub fn primary_sprite(&self, icon: i32) -> String {
+        if self.is_animated() {
+            format!("{}_01_001.png", self.basename(icon))
+        } else {
+            format!("{}_001.png", self.basename(icon))
+        }
+    }
+}
+
+/// Returned when parsing a string that isn't a known gamemode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseGamemodeError(pub String);
+
+impl std::fmt::Display for ParseGamemodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown gamemode `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseGamemodeError {}
+
+impl std::str::FromStr for Gamemode {
+    type Err = ParseGamemodeError;
+
+    /// Parses a gamemode by its name or the name the game uses for it internally (eg. `bird` for the UFO), ignoring case.
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str.trim().to_lowercase().as_str() {
+            "cube" | "player" | "icon" => Ok(Gamemode::Cube),
+            "ship" => Ok(Gamemode::Ship),
+            "ball" | "player_ball" => Ok(Gamemode::Ball),
+            "ufo" | "bird" => Ok(Gamemode::Ufo),
+            "wave" | "dart" => Ok(Gamemode::Wave),
+            "robot" => Ok(Gamemode::Robot),
+            "spider" => Ok(Gamemode::Spider),
+            "swing" | "swingcopter" => Ok(Gamemode::Swing),
+            "jetpack" => Ok(Gamemode::Jetpack),
+            _ => Err(ParseGamemodeError(str.to_string()))
+        }
+    }
+}
+
+impl std::fmt::Display for Gamemode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,203 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage, Frame, Delay};
+use image::codecs::gif::{GifEncoder, Repeat};
+
+use std::io::Write;
+use std::time::Duration;
+
+/// File formats a looping animation can be encoded to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+    /// Animated WebP, needs the `webp` feature
+    #[cfg(feature = "webp")]
+    WebP
+}
+
+impl AnimationFormat {
+    /// The file extension of this format, eg. `gif`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+            #[cfg(feature = "webp")]
+            AnimationFormat::WebP => "webp"
+        }
+    }
+}
+
+/// Anything that can go wrong while encoding an animation.
+#[derive(Debug)]
+pub enum EncodeError {
+    /// No frames were given
+    NoFrames,
+    /// The frame rate isn't a positive, finite number
+    InvalidFrameRate(f32),
+    /// The frames are too large for the format, eg. over 65535 pixels wide for GIFs
+    TooLarge { width: u32, height: u32 },
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Png(png::EncodingError),
+    /// libwebp failed to encode the animation
+    #[cfg(feature = "webp")]
+    WebP(String)
+}
+
+impl std::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::NoFrames => write!(f, "no frames to encode"),
+            EncodeError::InvalidFrameRate(fps) => write!(f, "invalid frame rate {}", fps),
+            EncodeError::TooLarge { width, height } => write!(f, "frames of {}x{} are too large for this format", width, height),
+            EncodeError::Io(err) => write!(f, "failed to write animation: {}", err),
+            EncodeError::Image(err) => write!(f, "failed to encode gif: {}", err),
+            EncodeError::Png(err) => write!(f, "failed to encode apng: {}", err),
+            #[cfg(feature = "webp")]
+            EncodeError::WebP(err) => write!(f, "failed to encode webp: {}", err)
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EncodeError::Io(err) => Some(err),
+            EncodeError::Image(err) => Some(err),
+            EncodeError::Png(err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for EncodeError {
+    fn from(err: std::io::Error) -> Self {
+        EncodeError::Io(err)
+    }
+}
+
+impl From<image::ImageError> for EncodeError {
+    fn from(err: image::ImageError) -> Self {
+        EncodeError::Image(err)
+    }
+}
+
+impl From<png::EncodingError> for EncodeError {
+    fn from(err: png::EncodingError) -> Self {
+        EncodeError::Png(err)
+    }
+}
+
+/// Alpha below which a pixel is fully transparent in a GIF, at or above which it's fully opaque
+const GIF_ALPHA_THRESHOLD: u8 = 128;
+
+/// Checks the frame rate and centers every frame on a transparent canvas the size of the largest one, since every format needs frames of one size.
+fn prepare_frames(frames: &[DynamicImage], fps: f32) -> Result<(Vec<RgbaImage>, u32, u32), EncodeError> {
+    if frames.is_empty() {
+        return Err(EncodeError::NoFrames);
+    }
+    if !fps.is_finite() || fps <= 0.0 {
+        return Err(EncodeError::InvalidFrameRate(fps));
+    }
+
+    let width = frames.iter().map(|frame| frame.width()).max().unwrap_or(0).max(1);
+    let height = frames.iter().map(|frame| frame.height()).max().unwrap_or(0).max(1);
+
+    let canvases = frames.iter().map(|frame| {
+        if frame.dimensions() == (width, height) {
+            return frame.to_rgba8();
+        }
+        let mut canvas = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+        let x = ((width - frame.width()) / 2) as i64;
+        let y = ((height - frame.height()) / 2) as i64;
+        image::imageops::overlay(&mut canvas, &frame.to_rgba8(), x, y);
+        return canvas;
+    }).collect();
+
+    return Ok((canvases, width, height));
+}
+
+/// Encodes frames as an infinitely looping GIF.
+///
+/// GIFs only have on/off transparency, so alpha is thresholded and transparent pixels are cleared to keep them from skewing the palette.
+pub fn encode_gif<W: Write>(frames: &[DynamicImage], fps: f32, writer: W) -> Result<(), EncodeError> {
+    let (canvases, width, height) = prepare_frames(frames, fps)?;
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(EncodeError::TooLarge { width, height });
+    }
+
+    let delay = Delay::from_saturating_duration(Duration::from_secs_f32(1.0 / fps));
+
+    let mut encoder = GifEncoder::new_with_speed(writer, 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(canvases.into_iter().map(|mut canvas| {
+        for pixel in canvas.pixels_mut() {
+            if pixel[3] < GIF_ALPHA_THRESHOLD {
+                *pixel = Rgba([0, 0, 0, 0]);
+            } else {
+                pixel[3] = 255;
+            }
+        }
+        return Frame::from_parts(canvas, 0, 0, delay);
+    }))?;
+
+    return Ok(());
+}
+
+/// Encodes frames as an infinitely looping APNG, keeping full alpha.
+pub fn encode_apng<W: Write>(frames: &[DynamicImage], fps: f32, writer: W) -> Result<(), EncodeError> {
+    let (canvases, width, height) = prepare_frames(frames, fps)?;
+
+    // delays are stored as a u16 fraction of a second; hundredths are plenty
+    let denominator = (fps * 100.0).round().clamp(1.0, u16::MAX as f32) as u16;
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(canvases.len() as u32, 0)?;
+    encoder.set_frame_delay(100, denominator)?;
+    encoder.set_dispose_op(png::DisposeOp::Background)?;
+    encoder.set_blend_op(png::BlendOp::Source)?;
+
+    let mut writer = encoder.write_header()?;
+    for canvas in &canvases {
+        writer.write_image_data(canvas.as_raw())?;
+    }
+    writer.finish()?;
+
+    return Ok(());
+}
+
+/// Encodes frames as an infinitely looping, lossless animated WebP.
+#[cfg(feature = "webp")]
+pub fn encode_webp<W: Write>(frames: &[DynamicImage], fps: f32, mut writer: W) -> Result<(), EncodeError> {
+    let (canvases, width, height) = prepare_frames(frames, fps)?;
+
+    let mut config = webp::WebPConfig::new().map_err(|_| EncodeError::WebP("failed to initialize config".to_string()))?;
+    config.lossless = 1;
+
+    let frame_time = 1000.0 / fps;
+    let mut encoder = webp::AnimEncoder::new(width, height, &config);
+    encoder.set_loop_count(0);
+    encoder.set_bgcolor([0, 0, 0, 0]);
+    for (i, canvas) in canvases.iter().enumerate() {
+        let timestamp = (i as f32 * frame_time).round() as i32;
+        encoder.add_frame(webp::AnimFrame::from_rgba(canvas.as_raw(), width, height, timestamp));
+    }
+
+    let data = encoder.try_encode().map_err(|err| EncodeError::WebP(format!("{:?}", err)))?;
+    writer.write_all(&data)?;
+
+    return Ok(());
+}
+
+/// Encodes frames, eg. from `render_animation`, as a looping animation of the given format at `fps` frames per second.
+///
+/// Frames of different sizes are centered on a canvas the size of the largest one.
+pub fn encode_animation<W: Write>(frames: &[DynamicImage], fps: f32, format: AnimationFormat, writer: W) -> Result<(), EncodeError> {
+    match format {
+        AnimationFormat::Gif => encode_gif(frames, fps, writer),
+        AnimationFormat::Apng => encode_apng(frames, fps, writer),
+        #[cfg(feature = "webp")]
+        AnimationFormat::WebP => encode_webp(frames, fps, writer)
+    }
+}
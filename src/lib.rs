@@ -2,14 +2,14 @@
 //! 
 //! gd-icon-renderer is a library for rendering Geometry Dash icons.
 //! 
-//! It uses the [image](https://crates.io/crates/image) crate and [imageproc](https://crates.io/crates/imageproc) crate for image manipulation and [plist](https://crates.io/crates/plist) for parsing plist files.
+//! It uses the [image](https://crates.io/crates/image) crate for image manipulation and [plist](https://crates.io/crates/plist) for parsing plist files.
 //!
 //! The main entrypoint is found in the [`renderer`](renderer/index.html) module.
 
-#![feature(lazy_cell)]
-
 pub mod assets;
+pub mod color;
 pub mod constants;
+pub mod encoder;
 pub mod renderer;
     
 #[cfg(test)]
@@ -22,11 +22,12 @@ mod tests {
     use std::time::Instant;
         
     #[test]
+    #[allow(clippy::eq_op)]
     fn render_test() {
-        let game_sheet_02 = load_spritesheet("assets/GJ_GameSheet02-uhd.plist");
-        let game_sheet_glow = load_spritesheet("assets/GJ_GameSheetGlow-uhd.plist");
-        let robot_sheet = load_animations("assets/Robot_AnimDesc2.plist");
-        let spider_sheet = load_animations("assets/Spider_AnimDesc2.plist");
+        let game_sheet_02 = load_spritesheet("assets/GJ_GameSheet02-uhd.plist").expect("loading spritesheet failed");
+        let game_sheet_glow = load_spritesheet("assets/GJ_GameSheetGlow-uhd.plist").expect("loading spritesheet failed");
+        let robot_sheet = load_animations("assets/Robot_AnimDesc2.plist").expect("loading animations failed");
+        let spider_sheet = load_animations("assets/Spider_AnimDesc2.plist").expect("loading animations failed");
 
         let start = Instant::now();
         let rendered_icon = render_icon("ship", 44, [0.0, 0.0, 0.0], [255.0/255.0, 125.0/255.0, 125.0/255.0], true, game_sheet_02, game_sheet_glow, robot_sheet, spider_sheet).expect("failed to render image");
@@ -36,4 +37,423 @@ mod tests {
 
         rendered_icon.save("rendered_icon.png").expect("saving image failed");
     }
+
+    #[test]
+    fn missing_assets_test() {
+        assert!(matches!(load_spritesheet("assets/does_not_exist.plist"), Err(AssetError::Io(_))));
+        assert!(matches!(load_animations("assets/does_not_exist.plist"), Err(AssetError::Io(_))));
+        assert!(matches!(IconAssets::load("assets/does_not_exist", TextureQuality::High), Err(AssetError::MissingFiles(files)) if files.len() == 6));
+    }
+
+    #[test]
+    fn assets_are_shareable_test() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<IconAssets>();
+    }
+
+    #[test]
+    fn texture_quality_test() {
+        assert_eq!(TextureQuality::from_file_name("GJ_GameSheet02-uhd.png"), TextureQuality::High);
+        assert_eq!(TextureQuality::from_file_name("GJ_GameSheet02-hd.png"), TextureQuality::Medium);
+        assert_eq!(TextureQuality::from_file_name("GJ_GameSheet02.png"), TextureQuality::Low);
+    }
+
+    /// A fixture sprite's name, size and offset
+    type TestSprite<'a> = (&'a str, (u32, u32), (f32, f32));
+
+    /// The .plist and .png of a spritesheet of opaque white sprites laid out side by side, given as their name, size and offset.
+    fn test_spritesheet_parts(texture_file_name: &str, sprites: &[TestSprite]) -> (String, Vec<u8>) {
+        let mut frames = String::new();
+        let mut left = 0;
+        for (name, (width, height), (x, y)) in sprites {
+            frames += &format!(r#"
+        <key>{}</key>
+        <dict>
+            <key>spriteOffset</key><string>{{{},{}}}</string>
+            <key>spriteSize</key><string>{{{},{}}}</string>
+            <key>spriteSourceSize</key><string>{{{},{}}}</string>
+            <key>textureRect</key><string>{{{{{},0}},{{{},{}}}}}</string>
+            <key>textureRotated</key><false/>
+        </dict>"#, name, x, y, width, height, width, height, left, width, height);
+            left += width;
+        }
+        let height = sprites.iter().map(|(_, size, _)| size.1).max().unwrap_or(1);
+
+        let plist = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>frames</key>
+    <dict>{}
+    </dict>
+    <key>metadata</key>
+    <dict>
+        <key>textureFileName</key><string>{}</string>
+        <key>size</key><string>{{{},{}}}</string>
+    </dict>
+</dict>
+</plist>"#, frames, texture_file_name, left.max(1), height);
+
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(left.max(1), height, image::Rgba([255, 255, 255, 255]))).write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png).expect("encoding texture failed");
+
+        return (plist, png);
+    }
+
+    fn test_spritesheet(texture_file_name: &str, sprites: &[TestSprite]) -> LoadedSpritesheet {
+        let (plist, png) = test_spritesheet_parts(texture_file_name, sprites);
+        return LoadedSpritesheet::from_parts(plist.as_bytes(), &png).expect("loading spritesheet failed");
+    }
+
+    /// -uhd assets with the given sprites in `GJ_GameSheet02`, and no glow sprites or animations.
+    fn test_assets(sprites: &[TestSprite]) -> IconAssets {
+        return IconAssets::new(test_spritesheet("GJ_GameSheet02-uhd.png", sprites), test_spritesheet("GJ_GameSheetGlow-uhd.png", &[]), Animations::new(), Animations::new());
+    }
+
+    #[test]
+    fn spritesheet_from_parts_test() {
+        let (plist, png) = test_spritesheet_parts("GJ_GameSheet02-uhd.png", &[("player_01_001.png", (2, 2), (0.0, 0.0))]);
+        // swaps out the sprite's rect, which is the only thing in the plist that's `{{0,0},{2,2}}`
+        let with_rect = |rect: &str| plist.replace("{{0,0},{2,2}}", rect);
+
+        let sheet = LoadedSpritesheet::from_parts(plist.as_bytes(), &png).expect("loading spritesheet failed");
+        assert!(sheet.icon_exists(constants::Gamemode::Cube, 1));
+        assert_eq!(sheet.max_icon_id(constants::Gamemode::Cube), Some(1));
+
+        assert!(matches!(LoadedSpritesheet::from_parts(with_rect("{{1,1},{2,2}}").as_bytes(), &png), Err(AssetError::SpriteOutOfBounds { .. })));
+        assert!(matches!(LoadedSpritesheet::from_parts(with_rect("{{2147483647,0},{2,2}}").as_bytes(), &png), Err(AssetError::SpriteOutOfBounds { .. })));
+        assert!(matches!(LoadedSpritesheet::from_parts(with_rect("{{0,0},{2}}").as_bytes(), &png), Err(AssetError::InvalidRect(_))));
+
+        // spritesheets that never went through `LoadedSpritesheet::new` are checked too
+        let unchecked = Spritesheet::from_bytes(with_rect("{{-1,0},{2,2}}").as_bytes()).expect("parsing spritesheet failed");
+        assert!(get_sprite(&unchecked, &sheet.texture, "player_01_001.png").is_none());
+    }
+
+    #[test]
+    fn gamemode_parse_test() {
+        use constants::Gamemode;
+
+        for gamemode in Gamemode::iter() {
+            assert_eq!(gamemode.to_string().parse::<Gamemode>(), Ok(gamemode));
+        }
+        assert_eq!("bird".parse::<Gamemode>(), Ok(Gamemode::Ufo));
+        assert_eq!("Dart".parse::<Gamemode>(), Ok(Gamemode::Wave));
+        assert_eq!("icon".parse::<Gamemode>(), Ok(Gamemode::Cube));
+        assert!("boat".parse::<Gamemode>().is_err());
+    }
+
+    #[test]
+    fn animation_key_test() {
+        assert_eq!(parse_animation_key("Robot_run_002.png"), Some(("run", 2)));
+        assert_eq!(parse_animation_key("Spider_fall_loop_010.png"), Some(("fall_loop", 10)));
+        assert_eq!(parse_animation_key("Robot_run.png"), None);
+
+        let part = "<dict><key>texture</key><string>robot_01_01_001.png</string><key>position</key><string>{1,2}</string><key>scale</key><string>{1,1}</string><key>rotation</key><string>0</string><key>flipped</key><string>{0,0}</string><key>zValue</key><string>1</string></dict>";
+        let plist = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><plist version=\"1.0\"><dict><key>animationContainer</key><dict><key>Robot_run_002.png</key><dict><key>sprite_1</key>{0}</dict><key>Robot_run_001.png</key><dict><key>sprite_1</key>{0}<key>sprite_2</key>{0}</dict></dict></dict></plist>",
+            part
+        );
+        let animations = load_animations_from_reader(std::io::Cursor::new(plist)).expect("loading animations failed");
+        let run = &animations["run"];
+        assert_eq!(run.frames.iter().map(|frame| frame.index).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(run.frame(1).map(|frame| frame.parts.iter().map(|part| part.name.as_str()).collect::<Vec<_>>()), Some(vec!["sprite_1", "sprite_2"]));
+        assert_eq!(list_animations(&animations), vec![("run".to_string(), 2)]);
+    }
+
+    #[test]
+    fn encode_animation_test() {
+        use encoder::*;
+        use image::{DynamicImage, RgbaImage, Rgba};
+
+        let frames = vec![
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]))),
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 6, Rgba([0, 0, 255, 100])))
+        ];
+
+        let mut gif = Vec::new();
+        encode_animation(&frames, 12.0, AnimationFormat::Gif, &mut gif).expect("encoding gif failed");
+        assert!(gif.starts_with(b"GIF89a"));
+
+        let mut apng = Vec::new();
+        encode_animation(&frames, 12.0, AnimationFormat::Apng, &mut apng).expect("encoding apng failed");
+        assert!(apng.windows(4).any(|chunk| chunk == b"acTL"));
+
+        assert!(matches!(encode_animation(&[], 12.0, AnimationFormat::Gif, Vec::new()), Err(EncodeError::NoFrames)));
+        assert!(matches!(encode_animation(&frames, 0.0, AnimationFormat::Apng, Vec::new()), Err(EncodeError::InvalidFrameRate(_))));
+    }
+
+    #[test]
+    fn render_layered_test() {
+        use image::{DynamicImage, GenericImageView, RgbaImage, Rgba};
+
+        let layer = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([255, 255, 255, 255])));
+
+        // whole pixel offsets are copied as-is, around an origin in the middle of the canvas
+        let rendered = render_layered(vec![layer.clone()], vec![Some((3.0, -1.0))], vec![Some(color::Color::rgb(1.0, 0.0, 0.0))], vec![None], vec![None]).expect("rendering layers failed");
+        assert_eq!(rendered.dimensions(), (8, 4));
+        assert_eq!(rendered.get_pixel(6, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(rendered.get_pixel(5, 0), Rgba([0, 0, 0, 0]));
+
+        // half a pixel over from the first layer, a layer is spread across three columns
+        let rendered = render_layered(vec![layer.clone(), layer.clone()], vec![Some((0.0, -2.0)), Some((0.5, 2.0))], vec![None, None], vec![None, None], vec![None, None]).expect("rendering layers failed");
+        let covered = |y: u32| (0..rendered.width()).filter(|&x| rendered.get_pixel(x, y)[3] > 0).count();
+        assert_eq!((covered(1), covered(rendered.height() - 2)), (2, 3));
+
+        // an odd-sized layer is kept on whole pixels rather than blurred over a half pixel
+        let odd = DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 3, Rgba([255, 255, 255, 255])));
+        let rendered = render_layered(vec![odd], vec![None], vec![None], vec![None], vec![None]).expect("rendering layers failed");
+        assert!(rendered.pixels().all(|p| p.2[3] == 0 || p.2[3] == 255));
+        assert_eq!(rendered.pixels().filter(|p| p.2[3] == 255).count(), 9);
+
+        // a soft edge filtered against transparent black stays white instead of picking up a dark fringe
+        let soft = DynamicImage::ImageRgba8(RgbaImage::from_fn(8, 8, |x, y| match x.min(y).min(7 - x).min(7 - y) {
+            0 => Rgba([0, 0, 0, 0]),
+            1 => Rgba([255, 255, 255, 128]),
+            _ => Rgba([255, 255, 255, 255])
+        }));
+        let rendered = render_layered(vec![soft], vec![None], vec![None], vec![None], vec![Some(30.0)]).expect("rendering layers failed");
+        let edges = rendered.pixels().filter(|p| p.2[3] > 0 && p.2[3] < 255).collect::<Vec<_>>();
+        assert!(!edges.is_empty());
+        assert!(edges.iter().all(|p| p.2[0] == 255 && p.2[1] == 255 && p.2[2] == 255));
+
+        // every layer needs all of its attributes
+        assert!(matches!(render_layered(vec![layer.clone(), layer], vec![None, None], vec![None], vec![None, None], vec![None, None]), Err(RenderError::MismatchedLayers)));
+    }
+
+    #[test]
+    fn color_spec_test() {
+        use constants::{ColorSpec, ColorError, COLORS, GAME_COLOR_COUNT};
+
+        assert_eq!(ColorSpec::Index(3).resolve(), Ok(COLORS[3]));
+        assert_eq!(ColorSpec::Index(COLORS.len()).resolve(), Err(ColorError::MissingColor(COLORS.len())));
+        assert_eq!(ColorSpec::Index(GAME_COLOR_COUNT - 1).resolve(), Err(ColorError::MissingColor(GAME_COLOR_COUNT - 1)));
+        assert_eq!(ColorSpec::Index(GAME_COLOR_COUNT).resolve(), Err(ColorError::IndexOutOfRange(GAME_COLOR_COUNT)));
+        assert_eq!(ColorSpec::from([255, 0, 51]).resolve(), Ok(color::Color::rgb(1.0, 0.0, 0.2)));
+        assert_eq!(ColorSpec::from("#ff0033").resolve(), Ok(color::Color::rgb(1.0, 0.0, 0.2)));
+        assert_eq!(ColorSpec::from("f03").resolve(), Ok(color::Color::rgb(1.0, 0.0, 0.2)));
+        assert_eq!(ColorSpec::from("orange").resolve(), Ok(COLORS[10]));
+        assert_eq!(ColorSpec::from("rgb(255, 0, 51)").resolve(), Ok(color::Color::rgb(1.0, 0.0, 0.2)));
+        assert!(matches!(ColorSpec::from("#ff00zz").resolve(), Err(ColorError::Invalid(_))));
+    }
+
+    #[test]
+    fn color_parse_test() {
+        use color::{Color, ParseColorError};
+
+        let pink = Color::rgb8(255, 125, 125);
+        assert_eq!("#ff7d7d".parse(), Ok(pink));
+        assert_eq!("FF7D7D".parse(), Ok(pink));
+        assert_eq!("rgb(255, 125, 125)".parse(), Ok(pink));
+        assert_eq!("Orange".parse(), Ok(constants::COLORS[10]));
+        assert_eq!("rgb(256, 0, 0)".parse::<Color>(), Err(ParseColorError("rgb(256, 0, 0)".to_string())));
+        assert!("chartreuse".parse::<Color>().is_err());
+
+        assert_eq!(pink.to_string(), "#ff7d7d");
+        assert_eq!(pink.to_string().parse(), Ok(pink));
+    }
+
+    #[test]
+    fn glow_color_test() {
+        use constants::Gamemode;
+        use color::Color;
+        use image::GenericImageView;
+
+        let mut assets = test_assets(&[("player_01_001.png", (2, 2), (0.0, 0.0))]);
+        assets.game_sheet_glow = test_spritesheet("GJ_GameSheetGlow-uhd.png", &[("player_01_glow_001.png", (4, 4), (0.0, 0.0))]);
+
+        // the glow sticks out a pixel past the cube on every side
+        let glow_at_corner = |options: RenderOptions| render(&assets, &options).expect("failed to render image").get_pixel(0, 0);
+        let options = RenderOptions::new(Gamemode::Cube, 1).glow(true);
+
+        assert_eq!(glow_at_corner(options.clone().primary_color([0, 0, 255]).secondary_color([255, 0, 0])), image::Rgba([255, 0, 0, 255]));
+        assert_eq!(glow_at_corner(options.clone().primary_color([0, 0, 255]).secondary_color([0, 0, 0])), image::Rgba([0, 0, 255, 255]));
+        assert_eq!(glow_at_corner(options.clone().primary_color([0, 0, 0]).secondary_color([0, 0, 0])), image::Rgba([255, 255, 255, 255]));
+        assert_eq!(glow_at_corner(options.clone().primary_color([0, 0, 0]).secondary_color([255, 0, 0]).glow_color(Color::rgb8(0, 255, 0))), image::Rgba([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn icon_sheet_test() {
+        let icons_dir = std::env::temp_dir().join(format!("gd-icon-renderer-icons-{}", std::process::id()));
+        std::fs::create_dir_all(&icons_dir).expect("creating icons dir failed");
+        let write_sheet = |basename: &str, quality: TextureQuality| {
+            let file_name = format!("{}{}", basename, quality.suffix());
+            let (plist, png) = test_spritesheet_parts(&format!("{}.png", file_name), &[(&format!("{}_001.png", basename), (2, 2), (0.0, 0.0))]);
+            std::fs::write(icons_dir.join(format!("{}.plist", file_name)), plist).expect("writing plist failed");
+            std::fs::write(icons_dir.join(format!("{}.png", file_name)), png).expect("writing texture failed");
+        };
+        write_sheet("player_05", TextureQuality::High);
+        write_sheet("player_05", TextureQuality::Low);
+
+        let assets = test_assets(&[("player_01_001.png", (2, 2), (0.0, 0.0))]).with_icons_dir(&icons_dir);
+
+        assert!(matches!(assets.icon_sheet("player_05"), Ok(IconSheet::Separate(sheet)) if sheet.icon_exists(constants::Gamemode::Cube, 5)));
+        assert!(matches!(assets.icon_sheet("player_01"), Ok(IconSheet::Combined { .. })));
+        assert!(matches!(assets.icon_sheet("player_07"), Ok(IconSheet::Combined { .. })));
+        assert_eq!(assets.max_icon_id(constants::Gamemode::Cube), Some(5));
+
+        // icons that weren't found aren't remembered, so ones added later still get picked up
+        write_sheet("player_07", TextureQuality::High);
+        assert!(matches!(assets.icon_sheet("player_07"), Ok(IconSheet::Separate(_))));
+
+        // a clone at another quality loads its own sheets rather than reusing the ones already cached
+        let low = assets.clone().with_quality(TextureQuality::Low);
+        assert!(matches!(low.icon_sheet("player_05"), Ok(IconSheet::Separate(sheet)) if sheet.spritesheet.texture_file_name == "player_05.png"));
+        assert!(matches!(assets.icon_sheet("player_05"), Ok(IconSheet::Separate(sheet)) if sheet.spritesheet.texture_file_name == "player_05-uhd.png"));
+
+        std::fs::remove_dir_all(&icons_dir).expect("removing icons dir failed");
+    }
+
+    #[test]
+    fn rider_test() {
+        use constants::Gamemode;
+        use image::GenericImageView;
+
+        let assets = test_assets(&[
+            ("player_05_001.png", (10, 10), (0.0, 0.0)),
+            ("ship_01_001.png", (120, 120), (0.0, 0.0)),
+            ("bird_01_001.png", (20, 10), (0.0, 0.0)),
+            ("jetpack_01_001.png", (120, 120), (0.0, 0.0))
+        ]);
+
+        // the red cube at its placement, offset from the blue vehicle's origin in texture pixels (y down)
+        let pixel_at = |gamemode: Gamemode, (x, y): (i32, i32)| {
+            let options = RenderOptions::new(gamemode, 1).primary_color([0, 0, 255]).secondary_color([0, 0, 255]).rider(Rider::new(5, ColorOptions::new([255, 0, 0], [255, 0, 0]))).anchored(true);
+            let rendered = render(&assets, &options).expect("failed to render image");
+            return rendered.get_pixel((rendered.width() as i32 / 2 + x) as u32, (rendered.height() as i32 / 2 + y) as u32);
+        };
+        let is_red = |pixel: image::Rgba<u8>| pixel[0] > 200 && pixel[2] < 50 && pixel[3] > 200;
+        let is_blue = |pixel: image::Rgba<u8>| pixel[0] < 50 && pixel[2] > 200 && pixel[3] > 200;
+
+        // above the UFO's dome, where nothing else is drawn
+        assert!(is_red(pixel_at(Gamemode::Ufo, (0, -20))));
+        assert!(is_blue(pixel_at(Gamemode::Ufo, (0, 0))));
+        // the ship is drawn over the cube sitting inside of it, the jetpack under the cube wearing it
+        assert!(is_blue(pixel_at(Gamemode::Ship, (0, -40))));
+        assert!(is_red(pixel_at(Gamemode::Jetpack, (24, -16))));
+    }
+
+    #[test]
+    fn anchored_test() {
+        use constants::Gamemode;
+        use image::GenericImageView;
+
+        // two cubes whose second layer sticks out in different directions
+        let assets = test_assets(&[
+            ("player_01_001.png", (10, 10), (0.0, 0.0)),
+            ("player_01_2_001.png", (4, 4), (20.0, 0.0)),
+            ("player_02_001.png", (10, 10), (0.0, 0.0)),
+            ("player_02_2_001.png", (4, 4), (-16.0, -12.0))
+        ]);
+        let options = |icon: i32| RenderOptions::new(Gamemode::Cube, icon).primary_color([255, 0, 0]).secondary_color([0, 255, 0]);
+
+        // cropping tightly keeps every pixel of the icon
+        let cropped = render(&assets, &options(1)).expect("failed to render image");
+        assert_eq!(cropped.dimensions(), (27, 10));
+
+        let first = render(&assets, &options(1).anchored(true)).expect("failed to render image");
+        let second = render(&assets, &options(2).anchored(true)).expect("failed to render image");
+        assert_ne!(first.dimensions(), second.dimensions());
+
+        // the primary layer is centered on the origin, which has to be the center of both images
+        for rendered in [first, second] {
+            let red: Vec<(u32, u32)> = rendered.pixels().filter(|p| p.2[0] > 200 && p.2[3] > 200).map(|p| (p.0, p.1)).collect();
+            let left = red.iter().map(|p| p.0).min().expect("no primary layer");
+            let top = red.iter().map(|p| p.1).min().expect("no primary layer");
+            assert_eq!((left + 5, top + 5), (rendered.width() / 2, rendered.height() / 2));
+        }
+    }
+
+    #[test]
+    fn output_size_test() {
+        use constants::Gamemode;
+        use image::GenericImageView;
+
+        let sprites = [("player_01_001.png", (10, 10), (0.0, 0.0))];
+        let assets = test_assets(&sprites);
+        let options = RenderOptions::new(Gamemode::Cube, 1).primary_color([255, 0, 0]);
+        let render_with = |options: RenderOptions| render(&assets, &options).expect("failed to render image");
+        let red = image::Rgba([255, 0, 0, 255]);
+        let clear = image::Rgba([0, 0, 0, 0]);
+
+        let content = render_with(options.clone().padding(3).background([0.0, 0.0, 1.0], 1.0));
+        assert_eq!(content.dimensions(), (16, 16));
+        assert_eq!((content.get_pixel(2, 2), content.get_pixel(3, 3)), (image::Rgba([0, 0, 255, 255]), red));
+
+        let exact = render_with(options.clone().size(OutputSize::Exact(30, 20)));
+        assert_eq!(exact.dimensions(), (30, 20));
+        assert_eq!((exact.get_pixel(9, 7), exact.get_pixel(10, 5), exact.get_pixel(19, 14)), (clear, red, red));
+
+        // anything reaching into the padding is cut off
+        let clipped = render_with(options.clone().size(OutputSize::Exact(12, 12)).padding(4));
+        assert_eq!((clipped.get_pixel(3, 3), clipped.get_pixel(4, 4), clipped.get_pixel(7, 7), clipped.get_pixel(8, 8)), (clear, red, red, clear));
+
+        // drawn at 4x and 2x rather than resized afterwards, so nearest neighbour keeps the edges hard
+        let fit = render_with(options.clone().size(OutputSize::Fit(40, 40)).filter(ResampleFilter::Nearest));
+        assert!(fit.pixels().all(|p| p.2 == red));
+        let fit = render_with(options.clone().size(OutputSize::Fit(50, 30)).padding(5).filter(ResampleFilter::Nearest));
+        assert_eq!((fit.get_pixel(14, 15), fit.get_pixel(15, 5), fit.get_pixel(34, 24), fit.get_pixel(35, 24)), (clear, red, red, clear));
+
+        // drawn at 4x to cover the 40x20 inside of the padding, with the rest cut off
+        let fill = render_with(options.clone().size(OutputSize::Fill(50, 30)).padding(5).filter(ResampleFilter::Nearest));
+        assert_eq!(fill.dimensions(), (50, 30));
+        assert_eq!((fill.get_pixel(4, 4), fill.get_pixel(5, 5), fill.get_pixel(44, 24), fill.get_pixel(45, 25)), (clear, red, red, clear));
+
+        // 10 pixels of a -uhd texture are 2.5 units, but 10 units of a low quality one
+        let game_units = OutputSize::GameUnits { width: 60, height: 60, pixels_per_unit: 4.0 };
+        let low_assets = test_assets(&sprites).with_quality(TextureQuality::Low);
+        let opaque = |rendered: &image::DynamicImage| rendered.pixels().filter(|p| p.2 == red).count();
+        let options_in_units = options.clone().size(game_units).filter(ResampleFilter::Nearest);
+        assert_eq!(opaque(&render_with(options_in_units.clone())), 10 * 10);
+        assert_eq!(opaque(&render(&low_assets, &options_in_units).expect("failed to render image")), 40 * 40);
+
+        let error = |options: RenderOptions| render(&assets, &options).err();
+        assert!(matches!(error(options.clone().size(OutputSize::Exact(0, 10))), Some(RenderError::InvalidSize { .. })));
+        assert!(matches!(error(options.clone().size(OutputSize::Fit(10, 10)).padding(5)), Some(RenderError::InvalidSize { .. })));
+        assert!(matches!(error(options.clone().size(OutputSize::Exact(100000, 100000))), Some(RenderError::TooLarge { .. })));
+        assert!(matches!(error(options.clone().padding(u32::MAX / 2 + 1)), Some(RenderError::TooLarge { .. })));
+    }
+
+    #[test]
+    fn scale_test() {
+        use constants::Gamemode;
+        use image::GenericImageView;
+
+        let assets = test_assets(&[("player_01_001.png", (10, 10), (0.0, 0.0))]);
+        let options = RenderOptions::new(Gamemode::Cube, 1).primary_color([255, 0, 0]);
+        let render_with = |options: RenderOptions| render(&assets, &options).expect("failed to render image");
+
+        let doubled = render_with(options.clone().scale(2.0).filter(ResampleFilter::Nearest));
+        assert_eq!(doubled.dimensions(), (20, 20));
+        assert!(doubled.pixels().all(|p| p.2 == image::Rgba([255, 0, 0, 255])));
+        assert_eq!(render_with(options.clone().scale(0.5).filter(ResampleFilter::Nearest)).dimensions(), (5, 5));
+
+        // at 1.5x the edges fall halfway across pixels, which only smooth filters blend
+        let is_partial = |p: &(u32, u32, image::Rgba<u8>)| p.2[3] > 0 && p.2[3] < 255;
+        assert!(!render_with(options.clone().scale(1.5).filter(ResampleFilter::Nearest)).pixels().any(|p| is_partial(&p)));
+        assert!(render_with(options.clone().scale(1.5).filter(ResampleFilter::Bilinear)).pixels().any(|p| is_partial(&p)));
+
+        let error = |options: RenderOptions| render(&assets, &options).err();
+        for scale in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(error(options.clone().scale(scale)), Some(RenderError::InvalidScale(_))));
+        }
+        assert!(matches!(error(options.clone().size(OutputSize::GameUnits { width: 10, height: 10, pixels_per_unit: -1.0 })), Some(RenderError::InvalidScale(_))));
+        assert!(matches!(error(options.clone().scale(1_000_000.0)), Some(RenderError::TooLarge { .. })));
+    }
+
+    #[test]
+    fn zany_part_test() {
+        use constants::Gamemode;
+        use image::GenericImageView;
+
+        // an 8x4 sprite whose offset puts it 4 pixels right of its part's center, in a part flipped horizontally and turned a quarter clockwise
+        let part = "<dict><key>texture</key><string>robot_01_01_001.png</string><key>position</key><string>{0,0}</string><key>scale</key><string>{1,1}</string><key>rotation</key><string>90</string><key>flipped</key><string>{1,0}</string><key>zValue</key><string>1</string></dict>";
+        let plist = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?><plist version=\"1.0\"><dict><key>animationContainer</key><dict><key>Robot_idle_001.png</key><dict><key>sprite_1</key>{}</dict></dict></dict></plist>", part);
+        let mut assets = test_assets(&[("robot_01_01_001.png", (8, 4), (4.0, 0.0))]);
+        assets.robot_animations = load_animations_from_reader(std::io::Cursor::new(plist)).expect("loading animations failed");
+
+        let rendered = render(&assets, &RenderOptions::new(Gamemode::Robot, 1).filter(ResampleFilter::Nearest).anchored(true)).expect("failed to render image");
+
+        // the offset is flipped to the left and then turned to point up, so the sprite stands 4 wide and 8 tall right above the origin
+        assert_eq!(rendered.dimensions(), (4, 16));
+        assert!(rendered.pixels().all(|p| (p.2[3] == 255) == (p.1 < 8)));
+    }
 }
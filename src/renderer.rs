@@ -1,129 +1,379 @@
 use image::*;
 use image::{DynamicImage, imageops};
-use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
 
-use std::cmp;
 
 use crate::assets;
-use crate::assets::{LoadedSpritesheet, Animations, Sprite};
+use crate::color::Color;
+use crate::constants::{Gamemode, LayerColor, ParseGamemodeError, ColorSpec, ColorError};
+use crate::assets::{LoadedSpritesheet, Animations, Sprite, AnimationFrame, AnimationPart, IconAssets, IconSheet, TextureQuality, AssetError};
 
-/// Internal function to easily transform an image
-fn transform(image: &DynamicImage, color: Option<[f32; 3]>, scale: Option<(f32, f32)>, rotation: Option<f32>) -> DynamicImage {
-    let mut transformed_image = image.clone();
+/// Errors that can occur while rendering an icon.
+#[derive(Debug)]
+pub enum RenderError {
+    /// The gamemode passed in isn't one of the known gamemodes.
+    UnknownGamemode(String),
+    /// The icon doesn't exist in the spritesheets. `tried` holds the required sprite names that could not be found.
+    MissingIcon { basename: String, tried: Vec<String> },
+    /// `render_layered` was given no layers to composite.
+    NoLayers,
+    /// `render_layered` was given a different number of positions, colors, scales or rotations than images.
+    MismatchedLayers,
+    /// The requested animation frame doesn't exist in the animation descriptions.
+    MissingAnimation { animation: String, frame: usize },
+    /// The icon's own spritesheet could not be loaded.
+    Asset(AssetError),
+    /// One of the colors isn't a valid color.
+    Color(ColorError),
+    /// The output size leaves no room for the icon; a side is zero, or no larger than the padding on both sides of it.
+    InvalidSize { width: u32, height: u32, padding: u32 },
+    /// The image, or the canvas it's drawn on, would be larger than [`MAX_IMAGE_SIZE`] on a side.
+    TooLarge { width: u32, height: u32 },
+    /// The scale, or pixels per unit, isn't a positive, finite number.
+    InvalidScale(f32)
+}
 
-    if let Some(color) = color {
-        let mut img_buffer = image.to_rgba8();
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::UnknownGamemode(gamemode) => write!(f, "unknown gamemode `{}`", gamemode),
+            RenderError::MissingIcon { basename, tried } => write!(f, "icon `{}` does not exist (tried {})", basename, tried.join(", ")),
+            RenderError::NoLayers => write!(f, "no layers to render"),
+            RenderError::MismatchedLayers => write!(f, "every layer needs a position, color, scale and rotation"),
+            RenderError::MissingAnimation { animation, frame } => write!(f, "frame {} of animation `{}` does not exist", frame, animation),
+            RenderError::Asset(err) => write!(f, "could not load icon spritesheet: {}", err),
+            RenderError::Color(err) => write!(f, "{}", err),
+            RenderError::InvalidSize { width, height, padding } => write!(f, "a {}x{} image with {} pixels of padding leaves no room for the icon", width, height, padding),
+            RenderError::TooLarge { width, height } => write!(f, "a {}x{} image is larger than {} pixels on a side", width, height, MAX_IMAGE_SIZE),
+            RenderError::InvalidScale(scale) => write!(f, "invalid scale {}", scale)
+        }
+    }
+}
 
-        for (_x, _y, pixel) in img_buffer.enumerate_pixels_mut() {
-            for channel in 0..3 {
-                pixel.0[channel] = (pixel.0[channel] as f32 * color[channel]) as u8;
-            }
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Asset(err) => Some(err),
+            RenderError::Color(err) => Some(err),
+            _ => None
         }
+    }
+}
 
-        transformed_image = DynamicImage::ImageRgba8(img_buffer);
+impl From<ParseGamemodeError> for RenderError {
+    fn from(err: ParseGamemodeError) -> Self {
+        RenderError::UnknownGamemode(err.0)
     }
+}
 
-    if let Some((scale_x, scale_y)) = scale {
-        let width = transformed_image.width();
-        let height = transformed_image.height();
+impl From<AssetError> for RenderError {
+    fn from(err: AssetError) -> Self {
+        RenderError::Asset(err)
+    }
+}
+
+impl From<ColorError> for RenderError {
+    fn from(err: ColorError) -> Self {
+        RenderError::Color(err)
+    }
+}
+
+/// The largest a rendered image, or the canvas it's drawn on, may be on either side, to keep a bad size or scale from allocating gigabytes.
+pub const MAX_IMAGE_SIZE: u32 = 4096;
+
+/// `TooLarge` if an image of the given size would be over [`MAX_IMAGE_SIZE`]
+fn check_image_size(width: u64, height: u64) -> Result<(), RenderError> {
+    if width > MAX_IMAGE_SIZE as u64 || height > MAX_IMAGE_SIZE as u64 {
+        return Err(RenderError::TooLarge { width: width.min(u32::MAX as u64) as u32, height: height.min(u32::MAX as u64) as u32 });
+    }
+    return Ok(());
+}
 
-        let abs_scale_x = scale_x.abs();
-        let abs_scale_y = scale_y.abs();
+/// `InvalidScale` unless `scale` is a positive, finite number
+fn check_scale(scale: f32) -> Result<f32, RenderError> {
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(RenderError::InvalidScale(scale));
+    }
+    return Ok(scale);
+}
 
-        transformed_image = transformed_image.resize_exact(
-            (width as f32 * abs_scale_x) as u32,
-            (height as f32 * abs_scale_y) as u32,
-            image::imageops::FilterType::Lanczos3
-        );
+/// Converts an image to premultiplied RGBA stored as floats from 0 to 1, which every layer is transformed and composited in.
+///
+/// Blending happens in gamma space like it does in the game, just without the dark fringes straight alpha gets when it's filtered.
+fn premultiply(image: &DynamicImage) -> Rgba32FImage {
+    let mut premultiplied = image.to_rgba32f();
 
-        if scale_x < 0.0 {
-            transformed_image = transformed_image.fliph();
+    for pixel in premultiplied.pixels_mut() {
+        let alpha = pixel[3];
+        for channel in 0..3 {
+            pixel[channel] *= alpha;
         }
-        if scale_y < 0.0 {
-            transformed_image = transformed_image.flipv();
+    }
+
+    return premultiplied;
+}
+
+/// Turns a premultiplied image back into a regular one.
+fn unpremultiply(image: &Rgba32FImage) -> DynamicImage {
+    let unpremultiplied = RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let pixel = image.get_pixel(x, y);
+        let alpha = pixel[3].clamp(0.0, 1.0);
+        if alpha <= 0.0 {
+            return Rgba([0, 0, 0, 0]);
         }
+
+        let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        return Rgba([to_u8(pixel[0] / alpha), to_u8(pixel[1] / alpha), to_u8(pixel[2] / alpha), to_u8(alpha)]);
+    });
+
+    return DynamicImage::ImageRgba8(unpremultiplied);
+}
+
+/// A 2D affine transform, mapping `(x, y)` to `(a * x + b * y + tx, c * x + d * y + ty)`. Pixel space, so y points down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Affine {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    tx: f32,
+    ty: f32
+}
+
+impl Affine {
+    const IDENTITY: Affine = Affine { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: 0.0, ty: 0.0 };
+
+    fn translate(x: f32, y: f32) -> Affine {
+        return Affine { tx: x, ty: y, ..Affine::IDENTITY };
+    }
+
+    /// Negative factors flip
+    fn scale(x: f32, y: f32) -> Affine {
+        return Affine { a: x, d: y, ..Affine::IDENTITY };
     }
 
-    if let Some(rotation) = rotation {
-        // lets not rotate if we dont need to
-        if rotation == 0.0 {
-            return transformed_image;
+    /// Clockwise on screen, like the game's rotations
+    fn rotate(degrees: f32) -> Affine {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        return Affine { a: cos, b: -sin, c: sin, d: cos, ..Affine::IDENTITY };
+    }
+
+    /// The transform that applies `other` and then `self`
+    fn then_after(&self, other: &Affine) -> Affine {
+        return Affine {
+            a: self.a * other.a + self.b * other.c,
+            b: self.a * other.b + self.b * other.d,
+            c: self.c * other.a + self.d * other.c,
+            d: self.c * other.b + self.d * other.d,
+            tx: self.a * other.tx + self.b * other.ty + self.tx,
+            ty: self.c * other.tx + self.d * other.ty + self.ty
+        };
+    }
+
+    /// Whether the transform only moves things around, without scaling, rotating or flipping them
+    fn is_translation(&self) -> bool {
+        return self.a == 1.0 && self.b == 0.0 && self.c == 0.0 && self.d == 1.0;
+    }
+
+    fn apply(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        return (self.a * x + self.b * y + self.tx, self.c * x + self.d * y + self.ty);
+    }
+
+    /// `None` if the transform squashes everything onto a line or point
+    fn inverse(&self) -> Option<Affine> {
+        let determinant = self.a * self.d - self.b * self.c;
+        if determinant.abs() < f32::EPSILON {
+            return None;
         }
 
-        let radians = rotation.to_radians();
+        let (a, b, c, d) = (self.d / determinant, -self.b / determinant, -self.c / determinant, self.a / determinant);
+        return Some(Affine { a, b, c, d, tx: -(a * self.tx + b * self.ty), ty: -(c * self.tx + d * self.ty) });
+    }
 
-        let (width, height) = transformed_image.dimensions();
-        
-        let trig_width = (width as f32 * radians.cos() + height as f32 * radians.sin())
-            .abs()
-            .ceil() as u32;
-        let trig_height = (width as f32 * radians.sin() + height as f32 * radians.cos())
-            .abs()
-            .ceil() as u32;
+    /// Where the corners of a `width` x `height` image end up
+    fn corners(&self, width: u32, height: u32) -> [(f32, f32); 4] {
+        let (width, height) = (width as f32, height as f32);
+        return [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)].map(|corner| self.apply(corner));
+    }
+}
 
-        let transform_x = ((trig_width as f32 / 2.0) - (width as f32 / 2.0)).ceil() as u32;
-        let transform_y = ((trig_height as f32 / 2.0) - (height as f32 / 2.0)).ceil() as u32;
+/// Draws a premultiplied `layer` over `canvas`, placed by `matrix` (from layer to canvas pixels) and sampled only once with `filter`, so layers land on sub-pixel positions without being blurred by repeated resampling.
+fn draw_layer(canvas: &mut Rgba32FImage, layer: &Rgba32FImage, matrix: &Affine, filter: ResampleFilter) {
+    // moved by whole pixels; nothing to resample
+    if matrix.is_translation() && matrix.tx.fract() == 0.0 && matrix.ty.fract() == 0.0 {
+        for (x, y, pixel) in layer.enumerate_pixels() {
+            blend(canvas, x as i64 + matrix.tx as i64, y as i64 + matrix.ty as i64, pixel.0);
+        }
+        return;
+    }
 
-        let mut canvas = ImageBuffer::new(cmp::max(trig_width, width), cmp::max(trig_height, height));
-        canvas.copy_from(&transformed_image, transform_x, transform_y).expect("couldnt copy from img");
-        canvas = rotate_about_center(&canvas, radians, Interpolation::Bilinear, Rgba([0, 0, 0, 0]));
-    
-        transformed_image = DynamicImage::ImageRgba8(canvas);
+    let Some(inverse) = matrix.inverse() else {
+        return;
+    };
+
+    // how far apart neighbouring canvas pixels are on the layer, to widen the filter when shrinking
+    let footprint_x = inverse.a.hypot(inverse.b).max(1.0);
+    let footprint_y = inverse.c.hypot(inverse.d).max(1.0);
+
+    let corners = matrix.corners(layer.width(), layer.height());
+    let margin = filter.support().ceil() + 1.0;
+    let left = (corners.iter().map(|c| c.0).fold(f32::INFINITY, f32::min) - margin).floor().clamp(0.0, canvas.width() as f32) as u32;
+    let top = (corners.iter().map(|c| c.1).fold(f32::INFINITY, f32::min) - margin).floor().clamp(0.0, canvas.height() as f32) as u32;
+    let right = (corners.iter().map(|c| c.0).fold(f32::NEG_INFINITY, f32::max) + margin).ceil().clamp(0.0, canvas.width() as f32) as u32;
+    let bottom = (corners.iter().map(|c| c.1).fold(f32::NEG_INFINITY, f32::max) + margin).ceil().clamp(0.0, canvas.height() as f32) as u32;
+
+    // without rotation, a column of the canvas always samples the same columns of the layer, and likewise for rows
+    if inverse.b == 0.0 && inverse.c == 0.0 {
+        let columns: Vec<Vec<(i64, f32)>> = (left..right).map(|x| filter.weights(inverse.a * (x as f32 + 0.5) + inverse.tx, footprint_x)).collect();
+        let rows: Vec<Vec<(i64, f32)>> = (top..bottom).map(|y| filter.weights(inverse.d * (y as f32 + 0.5) + inverse.ty, footprint_y)).collect();
+
+        for (y, row) in (top..bottom).zip(&rows) {
+            for (x, column) in (left..right).zip(&columns) {
+                blend(canvas, x as i64, y as i64, sample(layer, column, row));
+            }
+        }
+        return;
+    }
+
+    let (mut columns, mut rows) = (Vec::new(), Vec::new());
+    for y in top..bottom {
+        for x in left..right {
+            let (u, v) = inverse.apply((x as f32 + 0.5, y as f32 + 0.5));
+            filter.weights_into(u, footprint_x, &mut columns);
+            filter.weights_into(v, footprint_y, &mut rows);
+            blend(canvas, x as i64, y as i64, sample(layer, &columns, &rows));
+        }
+    }
+}
+
+/// Filters the pixels of a premultiplied `layer` at the given column and row weights. Pixels past the layer's edge count as transparent.
+fn sample(layer: &Rgba32FImage, columns: &[(i64, f32)], rows: &[(i64, f32)]) -> [f32; 4] {
+    let (width, height) = (layer.width() as i64, layer.height() as i64);
+
+    let mut sample = [0.0; 4];
+    for &(j, weight_y) in rows.iter().filter(|w| w.0 >= 0 && w.0 < height) {
+        for &(i, weight_x) in columns.iter().filter(|w| w.0 >= 0 && w.0 < width) {
+            let pixel = layer.get_pixel(i as u32, j as u32);
+            for channel in 0..4 {
+                sample[channel] += pixel[channel] * weight_x * weight_y;
+            }
+        }
+    }
+
+    // keep the pixel valid after filters that overshoot, eg. lanczos' ringing
+    let alpha = sample[3].clamp(0.0, 1.0);
+    return [sample[0].clamp(0.0, alpha), sample[1].clamp(0.0, alpha), sample[2].clamp(0.0, alpha), alpha];
+}
+
+/// Draws a premultiplied pixel over the canvas pixel at `x`, `y`, if there is one
+fn blend(canvas: &mut Rgba32FImage, x: i64, y: i64, pixel: [f32; 4]) {
+    if pixel[3] <= 0.0 || x < 0 || y < 0 || x >= canvas.width() as i64 || y >= canvas.height() as i64 {
+        return;
+    }
+
+    let below = canvas.get_pixel_mut(x as u32, y as u32);
+    let coverage = 1.0 - pixel[3];
+    for channel in 0..4 {
+        below[channel] = pixel[channel] + below[channel] * coverage;
     }
+}
 
-    return transformed_image;
+/// Tints a premultiplied image; tinting multiplies, so it works the same as on straight colors
+fn tint(image: &mut Rgba32FImage, color: Color) {
+    for pixel in image.pixels_mut() {
+        for (channel, tint) in color.to_array().iter().enumerate() {
+            pixel[channel] *= tint;
+        }
+    }
 }
 
 /// Mainly for internal use; given an array of images, their sizes and colors, tints and composits them into a single image
-pub fn render_layered(images: Vec<DynamicImage>, positions: Vec<Option<(f32, f32)>>, colors: Vec<Option<[f32; 3]>>, scales: Vec<Option<(f32, f32)>>, rotations: Vec<Option<f32>>) -> Result<DynamicImage, &'static str> {
-    let transformed: Vec<DynamicImage> = images.iter().enumerate().map(|(i, img)| {
-        transform(img, colors[i], scales[i], rotations[i])
-    }).collect();
-    let sizes: Vec<(i64, i64)> = transformed.iter().map(|img| {
-        (img.width() as i64, img.height() as i64)
-    }).collect();
+pub fn render_layered(images: Vec<DynamicImage>, positions: Vec<Option<(f32, f32)>>, colors: Vec<Option<Color>>, scales: Vec<Option<(f32, f32)>>, rotations: Vec<Option<f32>>) -> Result<DynamicImage, RenderError> {
+    if [positions.len(), colors.len(), scales.len(), rotations.len()].iter().any(|len| *len != images.len()) {
+        return Err(RenderError::MismatchedLayers);
+    }
 
-    let positions: Vec<(f32, f32)> = images.iter().enumerate().map(|(i, _v)| {
-        positions[i].unwrap_or((0.0, 0.0))
+    let layers = images.into_iter().enumerate().map(|(i, image)| {
+        // centered on its position, relative to the icon's origin
+        let (x, y) = positions[i].unwrap_or((0.0, 0.0));
+        let (scale_x, scale_y) = scales[i].unwrap_or((1.0, 1.0));
+        let matrix = Affine::translate(x, y)
+            .then_after(&Affine::rotate(rotations[i].unwrap_or(0.0)))
+            .then_after(&Affine::scale(scale_x, scale_y))
+            .then_after(&Affine::translate(-(image.width() as f32) / 2.0, -(image.height() as f32) / 2.0));
+
+        PlacedLayer { image, color: colors[i], matrix }
     }).collect();
 
-    let bounding_box = sizes
-        .iter()
-        .enumerate()
-        .map(|(i, &size)| {
-            let (width, height) = size;
-            let (x, y) = positions.get(i).cloned().unwrap_or((0.0, 0.0));
-            ((width as f32 + x.abs() * 2.0) as i32, (height as f32 + y.abs() * 2.0) as i32)
-        })
-        .fold((0, 0), |acc, size| {
-            (cmp::max(acc.0, size.0), cmp::max(acc.1, size.1))
-        });
+    return composite_layers(IconLayers { layers, glow_count: 0, snap_to: Some(0) }, 1.0, ResampleFilter::Lanczos3);
+}
 
-    let mut canvas = ImageBuffer::new(bounding_box.0 as u32, bounding_box.1 as u32);
+/// A sprite ready to be composited
+struct PlacedLayer {
+    image: DynamicImage,
+    color: Option<Color>,
+    /// From the sprite's pixels to texture pixels around the icon's origin
+    matrix: Affine
+}
 
-    // base
-    canvas.copy_from(
-        transformed.get(0).ok_or("Could not get image of inputted icon ID")?,
-        (bounding_box.0 as f32 / 2.0 + positions[0].0 as f32 - sizes[0].0 as f32 / 2.0) as u32,
-        (bounding_box.1 as f32 / 2.0 + positions[0].1 as f32 - sizes[0].1 as f32 / 2.0) as u32
-    ).expect("couldnt copy from img");
-    
-    // stacking
-    for (i, image) in transformed.iter().enumerate().skip(1) {
-        let x = (bounding_box.0 as f32 / 2.0 + positions[i].0 as f32 - image.width() as f32 / 2.0) as i64;
-        let y = (bounding_box.1 as f32 / 2.0 + positions[i].1 as f32 - image.height() as f32 / 2.0) as i64;
-    
-        imageops::overlay(&mut canvas, image, x, y)
+impl PlacedLayer {
+    /// A sprite centered on its offset from the icon's origin, like a non-robot/spider icon's layers
+    fn at_offset(image: DynamicImage, sprite: &Sprite, color: Option<Color>) -> PlacedLayer {
+        let matrix = Affine::translate(sprite.offset.0, -sprite.offset.1)
+            .then_after(&Affine::translate(-(image.width() as f32) / 2.0, -(image.height() as f32) / 2.0));
+        return PlacedLayer { image, color, matrix };
     }
+}
 
-    return Ok(DynamicImage::ImageRgba8(canvas));
+/// The layers of an icon, back to front
+struct IconLayers {
+    layers: Vec<PlacedLayer>,
+    /// How many of the layers at the start are glow
+    glow_count: usize,
+    /// The layer to keep on whole pixels when it's drawn 1:1, so the icon's main sprite is copied rather than resampled
+    snap_to: Option<usize>
 }
 
-fn is_black(c: [f32; 3]) -> bool {
-    c == [0.0, 0.0, 0.0]
+/// Tints and draws layers, back to front, `scale` times as large onto a canvas centered on the icon's origin.
+///
+/// Every layer is nudged by the same fraction of a pixel if that lands the `snap_to` layer on whole pixels, so odd-sized sprites stay sharp while keeping their places relative to each other.
+fn composite_layers(icon: IconLayers, scale: f32, filter: ResampleFilter) -> Result<DynamicImage, RenderError> {
+    let IconLayers { layers, snap_to, .. } = icon;
+    if layers.is_empty() {
+        return Err(RenderError::NoLayers);
+    }
+
+    let scale = Affine::scale(scale, scale);
+    let (shift_x, shift_y) = snap_to
+        .and_then(|index| layers.get(index))
+        .map(|layer| scale.then_after(&layer.matrix))
+        .filter(|matrix| matrix.is_translation())
+        .map(|matrix| (matrix.tx.round() - matrix.tx, matrix.ty.round() - matrix.ty))
+        .unwrap_or((0.0, 0.0));
+    let placement = Affine::translate(shift_x, shift_y).then_after(&scale);
+
+    // the canvas is centered on the icon's origin, which lands within half a pixel of a pixel corner
+    let (half_width, half_height) = layers.iter()
+        .flat_map(|layer| placement.then_after(&layer.matrix).corners(layer.image.width(), layer.image.height()))
+        .fold((0.0f32, 0.0f32), |acc, corner| (acc.0.max(corner.0.abs()), acc.1.max(corner.1.abs())));
+    let (half_width, half_height) = (half_width.ceil().max(1.0), half_height.ceil().max(1.0));
+    check_image_size(half_width as u64 * 2, half_height as u64 * 2)?;
+
+    let mut canvas = Rgba32FImage::new(half_width as u32 * 2, half_height as u32 * 2);
+    let origin = Affine::translate(half_width, half_height).then_after(&placement);
+
+    for layer in &layers {
+        let mut image = premultiply(&layer.image);
+        if let Some(color) = layer.color {
+            tint(&mut image, color);
+        }
+        draw_layer(&mut canvas, &image, &origin.then_after(&layer.matrix), filter);
+    }
+
+    return Ok(unpremultiply(&canvas));
 }
 
-fn crop_whitespace(img: DynamicImage) -> DynamicImage {
+/// Left, top, right and bottom of the non-transparent pixels in an image, or `None` if it's fully transparent
+fn content_bounds(img: &DynamicImage) -> Option<(u32, u32, u32, u32)> {
     let (width, height) = img.dimensions();
 
     let mut left = width;
@@ -143,49 +393,134 @@ fn crop_whitespace(img: DynamicImage) -> DynamicImage {
         }
     }
 
-    let cropped_image = img.clone().crop(left, top, right - left, bottom - top);
+    if left > right || top > bottom {
+        return None;
+    }
+
+    return Some((left, top, right, bottom));
+}
+
+fn crop_whitespace(img: DynamicImage) -> DynamicImage {
+    // nothing to crop to
+    let Some((left, top, right, bottom)) = content_bounds(&img) else {
+        return img;
+    };
+
+    // the bounds are inclusive
+    let cropped_image = img.clone().crop(left, top, right - left + 1, bottom - top + 1);
 
     return cropped_image
 }
 
-/// Renders out a non-robot/spider icon. You may be looking for `render_icon`.
-pub fn render_normal(basename: String, col1: [f32; 3], col2: [f32; 3], glow: bool, game_sheet_02: LoadedSpritesheet, game_sheet_glow: LoadedSpritesheet) -> Result<DynamicImage, &'static str> {
-    let glow_col = if is_black(col2) { if is_black(col1) { [1.0, 1.0, 1.0] } else { col1 } } else { col2 };
+/// Crops an image from `composite_layers` to its content while keeping the icon's origin in the middle, so it ends up at `(width / 2, height / 2)` of the result.
+fn crop_anchored(img: DynamicImage) -> DynamicImage {
+    let Some((left, top, right, bottom)) = content_bounds(&img) else {
+        return img;
+    };
 
-    let layers = vec![
-        (if glow || (is_black(col1) && is_black(col2)) {
-            assets::get_sprite_from_loaded(&game_sheet_glow, format!("{}_glow_001.png", basename))
-        } else {
-            None
-        }),
-        assets::get_sprite_from_loaded(&game_sheet_02, format!("{}_2_001.png", basename)),
-        assets::get_sprite_from_loaded(&game_sheet_02, format!("{}_3_001.png", basename)),
-        assets::get_sprite_from_loaded(&game_sheet_02, format!("{}_001.png", basename)),
-        assets::get_sprite_from_loaded(&game_sheet_02, format!("{}_extra_001.png", basename))
-    ];
-
-    let colors: Vec<Option<[f32; 3]>> = vec![
-        Some(glow_col),
-        Some(col2),
-        None,
-        Some(col1),
-        None
-    ];
-
-    let layered_images = render_layered(
-        layers.iter()
-            .filter_map(|s| s.as_ref().map(|(img, _spr)| img.to_owned()))
-            .collect(),
-        layers.iter()
-            .filter_map(|s| s.as_ref().map(|(_img, spr)| Some((spr.offset.0, spr.offset.1 * -1.0))))
-            .collect(), 
-        colors.iter()
-            .enumerate()
-            .filter_map(|(i, color)| layers[i].clone().map(|_| color.to_owned()))
-            .collect(),
-        vec![None, None, None, None, None],
-        vec![None, None, None, None, None]
-    )?;
+    // composite_layers puts the origin in the middle of its canvas, give or take half a pixel
+    let origin_x = img.width() / 2;
+    let origin_y = img.height() / 2;
+
+    let half_width = origin_x.saturating_sub(left).max((right + 1).saturating_sub(origin_x)).max(1);
+    let half_height = origin_y.saturating_sub(top).max((bottom + 1).saturating_sub(origin_y)).max(1);
+
+    let mut canvas = RgbaImage::new(half_width * 2, half_height * 2);
+    imageops::overlay(&mut canvas, &img, half_width as i64 - origin_x as i64, half_height as i64 - origin_y as i64);
+
+    return DynamicImage::ImageRgba8(canvas);
+}
+
+/// An icon's colors, resolved and ready to tint its layers with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IconColors {
+    pub primary: Color,
+    pub secondary: Color,
+    /// Overrides the glow color, which is otherwise derived from the primary and secondary colors.
+    pub glow_color: Option<Color>,
+    /// Whether to draw the glow outline. Icons with both colors black always have their glow drawn.
+    pub glow: bool
+}
+
+impl IconColors {
+    /// The given colors with no glow.
+    pub fn new(primary: impl Into<Color>, secondary: impl Into<Color>) -> IconColors {
+        return IconColors {
+            primary: primary.into(),
+            secondary: secondary.into(),
+            glow_color: None,
+            glow: false
+        }
+    }
+
+    pub fn glow_color(mut self, color: impl Into<Color>) -> IconColors {
+        self.glow_color = Some(color.into());
+        self
+    }
+
+    pub fn glow(mut self, glow: bool) -> IconColors {
+        self.glow = glow;
+        self
+    }
+
+    /// The color the glow is tinted with: `glow_color` if there is one, otherwise the secondary color, falling back to the primary color and then white if they're black.
+    pub fn glow_tint(&self) -> Color {
+        if let Some(color) = self.glow_color {
+            return color;
+        }
+        if self.secondary.is_black() { if self.primary.is_black() { Color::WHITE } else { self.primary } } else { self.secondary }
+    }
+
+    /// Whether the glow is drawn; an icon with both colors black would be invisible without it.
+    pub fn draws_glow(&self) -> bool {
+        return self.glow || (self.primary.is_black() && self.secondary.is_black());
+    }
+}
+
+/// The layers of a non-robot/spider icon, snapping to its primary sprite
+fn normal_layers(gamemode: Gamemode, icon: i32, colors: &IconColors, sheet: &IconSheet) -> Result<IconLayers, RenderError> {
+    let glow = colors.draws_glow();
+
+    let basename = gamemode.basename(icon);
+    let primary_sprite = gamemode.primary_sprite(icon);
+
+    // without the main sprite we'd end up rendering only parts of an icon
+    if !sheet.sheet().spritesheet.sprites.contains_key(&primary_sprite) {
+        return Err(RenderError::MissingIcon { basename, tried: vec![primary_sprite] });
+    }
+
+    let mut layers = IconLayers { layers: Vec::new(), glow_count: 0, snap_to: None };
+
+    for layer in gamemode.layers() {
+        let name = format!("{}{}_001.png", basename, layer.suffix);
+        let sprite = match layer.color {
+            LayerColor::Glow if !glow => None,
+            LayerColor::Glow => assets::get_sprite_from_loaded(sheet.glow_sheet(), &name),
+            _ => assets::get_sprite_from_loaded(sheet.sheet(), &name)
+        };
+
+        if let Some((image, sprite)) = sprite {
+            match layer.color {
+                LayerColor::Glow => layers.glow_count += 1,
+                LayerColor::Primary => layers.snap_to = Some(layers.layers.len()),
+                _ => {}
+            }
+            let color = match layer.color {
+                LayerColor::Primary => Some(colors.primary),
+                LayerColor::Secondary => Some(colors.secondary),
+                LayerColor::Glow => Some(colors.glow_tint()),
+                LayerColor::Untinted => None
+            };
+            layers.layers.push(PlacedLayer::at_offset(image, &sprite, color));
+        }
+    }
+
+    return Ok(layers);
+}
+
+/// Renders out a non-robot/spider icon. You may be looking for `render`.
+pub fn render_normal(gamemode: Gamemode, icon: i32, colors: &IconColors, sheet: &IconSheet) -> Result<DynamicImage, RenderError> {
+    let layered_images = composite_layers(normal_layers(gamemode, icon, colors, sheet)?, 1.0, ResampleFilter::Lanczos3)?;
 
     return Ok(crop_whitespace(layered_images));
 }
@@ -194,15 +529,30 @@ fn flip(scale: (f32, f32), flipped: (bool, bool)) -> (f32, f32) {
     (scale.0 * (if flipped.0 { -1 } else { 1 }) as f32, scale.1 * (if flipped.1 { -1 } else { 1 }) as f32)
 }
 
-/// Renders out a robot/spider icon. You may be looking for `render_icon`.
-pub fn render_zany(basename: String, col1: [f32; 3], col2: [f32; 3], glow: bool, game_sheet_02: LoadedSpritesheet, _game_sheet_glow: LoadedSpritesheet, animations: Animations) -> Result<DynamicImage, &'static str> {
-    let glow_col = if is_black(col2) { if is_black(col1) { [1.0, 1.0, 1.0] } else { col1 } } else { col2 };
-    let glow = glow || (is_black(col1) && is_black(col2));
+/// Renders out a robot/spider icon posed as in `frame`, eg. the first frame of the `idle` animation. You may be looking for `render`.
+///
+/// `gamemode` must be an [animated](Gamemode::is_animated) gamemode. `quality` must match the quality of `sheet`, as animations are described in in-game points.
+pub fn render_zany(gamemode: Gamemode, icon: i32, colors: &IconColors, sheet: &IconSheet, frame: &AnimationFrame, quality: TextureQuality) -> Result<DynamicImage, RenderError> {
+    let layered_images = composite_layers(zany_layers(gamemode, icon, colors, sheet, frame, quality)?, 1.0, ResampleFilter::Lanczos3)?;
 
-    let mut anim = animations.get("Robot_idle_001.png").unwrap_or_else(|| animations.get("Spider_idle_001.png").expect("no animations found")).clone();
+    return Ok(crop_whitespace(layered_images));
+}
+
+/// The layers of a robot/spider icon posed as in `frame`.
+///
+/// Nothing is snapped to whole pixels, since the parts move between frames and snapping would make the icon jitter.
+fn zany_layers(gamemode: Gamemode, icon: i32, colors: &IconColors, sheet: &IconSheet, frame: &AnimationFrame, quality: TextureQuality) -> Result<IconLayers, RenderError> {
+    let factor = quality.scale_factor();
+    let basename = gamemode.basename(icon);
+    let glow = colors.draws_glow();
+
+    let mut anim: Vec<&AnimationPart> = frame.parts.iter().collect();
     anim.sort_by_key(|spr| spr.z);
 
-    let mut layers: Vec<(Option<(DynamicImage, Sprite)>, (f32, f32), (f32, f32), f64, bool, Option<[f32; 3]>)> = Vec::new();
+    // glow goes behind everything else
+    let mut glow_layers: Vec<PlacedLayer> = Vec::new();
+    let mut layers: Vec<PlacedLayer> = Vec::new();
+    let mut missing: Vec<String> = Vec::new();
 
     for a in anim {
         let texture_name = a.texture.replace("spider_01", &basename).replace("robot_01", &basename);
@@ -212,58 +562,480 @@ pub fn render_zany(basename: String, col1: [f32; 3], col2: [f32; 3], glow: bool,
             texture_name.clone(),
             texture_name.replace("_001.png", "_extra_001.png")
         ];
-        let mut colors = vec![
-            Some(col2),
+        let mut layer_colors = vec![
+            Some(colors.secondary),
             None,
-            Some(col1),
+            Some(colors.primary),
             None
         ];
 
         if glow {
             names.push(texture_name.replace("_001.png", "_glow_001.png"));
-            colors.push(Some(glow_col));
+            layer_colors.push(Some(colors.glow_tint()));
         }
 
-        layers.extend(names.iter().enumerate().map(|(i, v)| {
-            (
-                assets::get_sprite_from_loaded(&game_sheet_02, v.clone()),
-                a.position,
-                flip(a.scale, a.flipped),
-                a.rotation,
-                glow && i == names.len() - 1,
-                colors[i]
-            )
-        }))
-    }
-
-    // put glow b4 everything else
-    layers.sort_by_key(|t| if t.4 { 0 } else { 1 });
-
-    let layers_r = layers.iter()
-        .filter(|v| v.0.is_some())
-        .filter_map(|(opt_sprite, pos, scale, rot, glow, color)| opt_sprite.clone().map(|sprite| ((sprite.0, sprite.1), *pos, *scale, *rot, *glow, *color)))
-        .collect::<Vec<((DynamicImage, Sprite), (f32, f32), (f32, f32), f64, bool, Option<[f32; 3]>)>>();
-
-    let layered_images = render_layered(
-        layers_r.iter().map(|t| t.0.0.clone()).collect(),
-        layers_r.iter().map(|t| Some((t.0.1.offset.0 + t.1.0 * 4.0, t.0.1.offset.1 * -1.0  + t.1.1 * -4.0))).collect(),
-        layers_r.iter().map(|t| t.5).collect(),
-        layers_r.iter().map(|t| Some(t.2)).collect(),
-        layers_r.iter().map(|t| Some(t.3 as f32)).collect()
-    )?;
+        if !sheet.sheet().spritesheet.sprites.contains_key(&texture_name) && !missing.contains(&texture_name) {
+            missing.push(texture_name.clone());
+        }
 
-    return Ok(crop_whitespace(layered_images));
+        // from the part's space (in points, y up) to texture pixels around the icon's origin
+        let (scale_x, scale_y) = flip(a.scale, a.flipped);
+        let part_matrix = Affine::translate(a.position.0 * factor, -a.position.1 * factor)
+            .then_after(&Affine::rotate(a.rotation as f32))
+            .then_after(&Affine::scale(scale_x, scale_y));
+
+        for (i, name) in names.iter().enumerate() {
+            let is_glow = glow && i == names.len() - 1;
+            let Some((image, sprite)) = assets::get_sprite_from_loaded(if is_glow { sheet.glow_sheet() } else { sheet.sheet() }, name) else {
+                continue;
+            };
+
+            // the sprite's offset lies within the part, so it's scaled, flipped and rotated along with it
+            let layer = PlacedLayer::at_offset(image, &sprite, layer_colors[i]);
+            let layer = PlacedLayer { matrix: part_matrix.then_after(&layer.matrix), ..layer };
+
+            if is_glow { glow_layers.push(layer) } else { layers.push(layer) }
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(RenderError::MissingIcon { basename, tried: missing });
+    }
+
+    let glow_count = glow_layers.len();
+    glow_layers.extend(layers);
+    return Ok(IconLayers { layers: glow_layers, glow_count, snap_to: None });
 }
 
-/// The main entrypoint for icon rendering; this should be all you need to render out an icon.
-///
-/// `gamemode` must be one of `cube`, `ship`, `ball`, `ufo`, `wave`, `robot`, or `spider`
-pub fn render_icon(gamemode_str: &str, icon: i32, col1: [f32; 3], col2: [f32; 3], glow: bool, game_sheet_02: LoadedSpritesheet, game_sheet_glow: LoadedSpritesheet, robot_animations: Animations, spider_animations: Animations) -> Result<DynamicImage, &'static str> {
-    let gamemode = crate::constants::GAMEMODES.get(gamemode_str).ok_or("Invalid gamemode")?;
+/// The colors an icon is rendered with, as given; see [`IconColors`] for them resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorOptions {
+    pub primary: ColorSpec,
+    pub secondary: ColorSpec,
+    /// Overrides the glow color, which is otherwise derived from the primary and secondary colors.
+    pub glow_color: Option<ColorSpec>,
+    /// Whether to draw the glow outline. Icons with both colors black always have their glow drawn.
+    pub glow: bool
+}
 
-    if gamemode.zany {
-        return Ok(render_zany(format!("{}{:02}", gamemode.prefix, icon), col1, col2, glow, game_sheet_02, game_sheet_glow, if gamemode_str == "robot" { robot_animations } else { spider_animations }))?
-    } else {
-        return Ok(render_normal(format!("{}{:02}", gamemode.prefix, icon), col1, col2, glow, game_sheet_02, game_sheet_glow))?
+impl ColorOptions {
+    /// The given colors with no glow.
+    pub fn new(primary: impl Into<ColorSpec>, secondary: impl Into<ColorSpec>) -> ColorOptions {
+        return ColorOptions {
+            primary: primary.into(),
+            secondary: secondary.into(),
+            glow_color: None,
+            glow: false
+        }
     }
-}
\ No newline at end of file
+
+    pub fn glow_color(mut self, color: impl Into<ColorSpec>) -> ColorOptions {
+        self.glow_color = Some(color.into());
+        self
+    }
+
+    pub fn glow(mut self, glow: bool) -> ColorOptions {
+        self.glow = glow;
+        self
+    }
+
+    pub fn resolve(&self) -> Result<IconColors, ColorError> {
+        return Ok(IconColors {
+            primary: self.primary.resolve()?,
+            secondary: self.secondary.resolve()?,
+            glow_color: self.glow_color.as_ref().map(ColorSpec::resolve).transpose()?,
+            glow: self.glow
+        });
+    }
+}
+
+/// The player's cube, drawn inside of a ship, UFO or jetpack like in the game's garage preview.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rider {
+    pub icon: i32,
+    pub colors: ColorOptions
+}
+
+impl Rider {
+    /// Cube `icon` with the given colors.
+    pub fn new(icon: i32, colors: ColorOptions) -> Rider {
+        return Rider { icon, colors };
+    }
+}
+
+/// How sprites are resampled when they're scaled or rotated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResampleFilter {
+    /// Keeps hard pixel edges, for crisp pixel-art-style previews
+    Nearest,
+    Bilinear,
+    CatmullRom,
+    /// Sharpest smooth filter, the default
+    Lanczos3
+}
+
+impl ResampleFilter {
+    /// How far the filter reaches, in pixels
+    fn support(&self) -> f32 {
+        match self {
+            ResampleFilter::Nearest => 0.5,
+            ResampleFilter::Bilinear => 1.0,
+            ResampleFilter::CatmullRom => 2.0,
+            ResampleFilter::Lanczos3 => 3.0
+        }
+    }
+
+    fn kernel(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            ResampleFilter::Nearest => if x < 0.5 { 1.0 } else { 0.0 },
+            ResampleFilter::Bilinear => (1.0 - x).max(0.0),
+            ResampleFilter::CatmullRom => {
+                if x < 1.0 {
+                    1.5 * x * x * x - 2.5 * x * x + 1.0
+                } else if x < 2.0 {
+                    -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+                } else {
+                    0.0
+                }
+            },
+            ResampleFilter::Lanczos3 => {
+                if x < f32::EPSILON {
+                    1.0
+                } else if x < 3.0 {
+                    let pi_x = std::f32::consts::PI * x;
+                    3.0 * pi_x.sin() * (pi_x / 3.0).sin() / (pi_x * pi_x)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// The pixels around `center` (a position along one axis of an image) and how much they count, with the kernel stretched by `footprint` and the weights adding up to 1
+    fn weights(&self, center: f32, footprint: f32) -> Vec<(i64, f32)> {
+        let mut weights = Vec::new();
+        self.weights_into(center, footprint, &mut weights);
+        return weights;
+    }
+
+    /// Same as `weights`, but reuses a buffer
+    fn weights_into(&self, center: f32, footprint: f32, weights: &mut Vec<(i64, f32)>) {
+        weights.clear();
+        if *self == ResampleFilter::Nearest {
+            weights.push((center.floor() as i64, 1.0));
+            return;
+        }
+
+        let radius = self.support() * footprint;
+        let mut total = 0.0;
+        for i in (center - radius).floor() as i64..=(center + radius).ceil() as i64 {
+            let weight = self.kernel((i as f32 + 0.5 - center) / footprint);
+            if weight != 0.0 {
+                weights.push((i, weight));
+                total += weight;
+            }
+        }
+
+        if total.abs() > f32::EPSILON {
+            for weight in weights.iter_mut() {
+                weight.1 /= total;
+            }
+        }
+    }
+}
+
+/// How large the rendered image is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputSize {
+    /// Just large enough to fit the icon, scaled by [`scale`](RenderOptions::scale)
+    Content,
+    /// Exactly `width` x `height`, with the icon scaled by [`scale`](RenderOptions::scale) and centered. Anything that doesn't fit is cut off.
+    Exact(u32, u32),
+    /// Exactly `width` x `height`, with the icon drawn as large as fits while keeping its aspect ratio, and centered. [`scale`](RenderOptions::scale) is ignored.
+    Fit(u32, u32),
+    /// Exactly `width` x `height`, with the icon drawn as small as covers it while keeping its aspect ratio, and centered. [`scale`](RenderOptions::scale) is ignored and anything that doesn't fit is cut off.
+    Fill(u32, u32),
+    /// Exactly `width` x `height`, with the icon drawn at `pixels_per_unit` pixels per in-game unit (a block is 30 units) and centered, so every gamemode and texture quality comes out at the same scale. [`scale`](RenderOptions::scale) is ignored and anything that doesn't fit is cut off.
+    GameUnits { width: u32, height: u32, pixels_per_unit: f32 }
+}
+
+/// Everything that controls how an icon is rendered out. Start with [`RenderOptions::new`] and chain the setters for anything you want to change.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderOptions {
+    pub gamemode: Gamemode,
+    pub icon: i32,
+    pub colors: ColorOptions,
+    /// Multiplier for the size of the resulting image, which must be positive. Every sprite is drawn at this scale rather than the finished image being resized.
+    pub scale: f32,
+    /// How sprites are resampled when they're scaled or rotated.
+    pub filter: ResampleFilter,
+    /// How large the resulting image is.
+    pub size: OutputSize,
+    /// Transparent (or background colored) pixels kept clear around the icon. Added onto the image for [`OutputSize::Content`], taken out of it otherwise.
+    pub padding: u32,
+    /// Color and opacity (from 0 to 1) to fill the image with behind the icon.
+    pub background: Option<(Color, f32)>,
+    /// The animation to pose robots and spiders in, eg. `run`. See [`IconAssets::list_animations`] for what's available.
+    pub animation: String,
+    /// The frame of `animation` to pose robots and spiders in, starting at 1.
+    pub animation_frame: usize,
+    /// The cube to draw inside of the vehicle. Ignored for gamemodes without a [rider placement](Gamemode::rider_placement).
+    pub rider: Option<Rider>,
+    /// Keeps the icon's origin (the point the game positions the player at) at `(width / 2, height / 2)` of the image instead of tightly cropping to the icon, so that different icons and animation frames line up when stacked.
+    pub anchored: bool
+}
+
+impl RenderOptions {
+    /// Options for rendering `icon` of `gamemode` with the default colors (green and cyan), no glow and no extra sizing.
+    pub fn new(gamemode: Gamemode, icon: i32) -> RenderOptions {
+        return RenderOptions {
+            gamemode,
+            icon,
+            colors: ColorOptions::new(ColorSpec::Index(0), ColorSpec::Index(3)),
+            scale: 1.0,
+            filter: ResampleFilter::Lanczos3,
+            size: OutputSize::Content,
+            padding: 0,
+            background: None,
+            animation: "idle".to_string(),
+            animation_frame: 1,
+            rider: None,
+            anchored: false
+        }
+    }
+
+    pub fn gamemode(mut self, gamemode: Gamemode) -> RenderOptions {
+        self.gamemode = gamemode;
+        self
+    }
+
+    pub fn icon(mut self, icon: i32) -> RenderOptions {
+        self.icon = icon;
+        self
+    }
+
+    pub fn colors(mut self, colors: ColorOptions) -> RenderOptions {
+        self.colors = colors;
+        self
+    }
+
+    pub fn primary_color(mut self, color: impl Into<ColorSpec>) -> RenderOptions {
+        self.colors.primary = color.into();
+        self
+    }
+
+    pub fn secondary_color(mut self, color: impl Into<ColorSpec>) -> RenderOptions {
+        self.colors.secondary = color.into();
+        self
+    }
+
+    pub fn glow_color(mut self, color: impl Into<ColorSpec>) -> RenderOptions {
+        self.colors = self.colors.glow_color(color);
+        self
+    }
+
+    pub fn glow(mut self, glow: bool) -> RenderOptions {
+        self.colors = self.colors.glow(glow);
+        self
+    }
+
+    pub fn scale(mut self, scale: f32) -> RenderOptions {
+        self.scale = scale;
+        self
+    }
+
+    pub fn filter(mut self, filter: ResampleFilter) -> RenderOptions {
+        self.filter = filter;
+        self
+    }
+
+    pub fn size(mut self, size: OutputSize) -> RenderOptions {
+        self.size = size;
+        self
+    }
+
+    pub fn padding(mut self, padding: u32) -> RenderOptions {
+        self.padding = padding;
+        self
+    }
+
+    pub fn background(mut self, color: impl Into<Color>, alpha: f32) -> RenderOptions {
+        self.background = Some((color.into(), alpha));
+        self
+    }
+
+    pub fn animation(mut self, animation: &str, frame: usize) -> RenderOptions {
+        self.animation = animation.to_string();
+        self.animation_frame = frame;
+        self
+    }
+
+    pub fn rider(mut self, rider: Rider) -> RenderOptions {
+        self.rider = Some(rider);
+        self
+    }
+
+    pub fn anchored(mut self, anchored: bool) -> RenderOptions {
+        self.anchored = anchored;
+        self
+    }
+}
+
+/// Checks that the size and padding of `options` leave room for the icon without going over [`MAX_IMAGE_SIZE`].
+fn check_output_size(options: &RenderOptions) -> Result<(), RenderError> {
+    let padding = options.padding;
+
+    match options.size {
+        // the icon's size isn't known yet; `finish` checks it
+        OutputSize::Content => return check_image_size(padding as u64 * 2, padding as u64 * 2),
+        OutputSize::Exact(width, height) | OutputSize::Fit(width, height) | OutputSize::Fill(width, height) | OutputSize::GameUnits { width, height, .. } => {
+            check_image_size(width as u64, height as u64)?;
+            if width as u64 <= padding as u64 * 2 || height as u64 <= padding as u64 * 2 {
+                return Err(RenderError::InvalidSize { width, height, padding });
+            }
+            return Ok(());
+        }
+    }
+}
+
+/// How large `layers` are at a scale of 1, measured around the origin if they'll be anchored.
+fn content_size(layers: &[PlacedLayer], anchored: bool) -> (f32, f32) {
+    let corners: Vec<(f32, f32)> = layers.iter().flat_map(|layer| layer.matrix.corners(layer.image.width(), layer.image.height())).collect();
+
+    let (content_width, content_height) = if anchored {
+        let half_width = corners.iter().fold(0.0f32, |acc, corner| acc.max(corner.0.abs()));
+        let half_height = corners.iter().fold(0.0f32, |acc, corner| acc.max(corner.1.abs()));
+        (half_width * 2.0, half_height * 2.0)
+    } else {
+        let extent = |axis: fn(&(f32, f32)) -> f32| corners.iter().map(axis).fold(f32::NEG_INFINITY, f32::max) - corners.iter().map(axis).fold(f32::INFINITY, f32::min);
+        (extent(|corner| corner.0), extent(|corner| corner.1))
+    };
+
+    return (content_width.max(1.0), content_height.max(1.0));
+}
+
+/// Applies the size, padding and background of `options` to a rendered icon, which has already been drawn at the right scale.
+fn finish(img: DynamicImage, options: &RenderOptions) -> Result<DynamicImage, RenderError> {
+    let padding = options.padding;
+    let background = match options.background {
+        Some((color, alpha)) => {
+            let [r, g, b] = color.to_rgb8();
+            Rgba([r, g, b, (alpha.clamp(0.0, 1.0) * 255.0).round() as u8])
+        },
+        None => Rgba([0, 0, 0, 0])
+    };
+
+    let (width, height) = match options.size {
+        OutputSize::Content => {
+            if padding == 0 && options.background.is_none() {
+                return Ok(img);
+            }
+
+            let (width, height) = (img.width() as u64 + padding as u64 * 2, img.height() as u64 + padding as u64 * 2);
+            check_image_size(width, height)?;
+            (width as u32, height as u32)
+        },
+        OutputSize::Exact(width, height) | OutputSize::Fit(width, height) | OutputSize::Fill(width, height) | OutputSize::GameUnits { width, height, .. } => (width, height)
+    };
+
+    // the icon is centered inside of the padding and cut off at its edge
+    let (inner_width, inner_height) = (width - padding * 2, height - padding * 2);
+    let mut inner = RgbaImage::new(inner_width, inner_height);
+    imageops::overlay(
+        &mut inner,
+        &img,
+        (inner_width as i64 - img.width() as i64) / 2,
+        (inner_height as i64 - img.height() as i64) / 2
+    );
+
+    let mut canvas = RgbaImage::from_pixel(width, height, background);
+    imageops::overlay(&mut canvas, &inner, padding as i64, padding as i64);
+
+    return Ok(DynamicImage::ImageRgba8(canvas));
+}
+
+/// The main entrypoint for icon rendering; renders out an icon as described by `options`.
+pub fn render(assets: &IconAssets, options: &RenderOptions) -> Result<DynamicImage, RenderError> {
+    let gamemode = options.gamemode;
+
+    let basename = gamemode.basename(options.icon);
+    let icon_sheet = assets.icon_sheet(&basename)?;
+
+    let colors = options.colors.resolve()?;
+    check_output_size(options)?;
+
+    let layers = if gamemode.is_animated() {
+        let frame = assets.animations(gamemode)
+            .and_then(|animations| animations.get(&options.animation))
+            .and_then(|animation| animation.frame(options.animation_frame))
+            .ok_or_else(|| RenderError::MissingAnimation { animation: options.animation.clone(), frame: options.animation_frame })?;
+
+        zany_layers(gamemode, options.icon, &colors, &icon_sheet, frame, assets.quality)?
+    } else {
+        let mut layers = normal_layers(gamemode, options.icon, &colors, &icon_sheet)?;
+
+        if let (Some(rider), Some(placement)) = (&options.rider, gamemode.rider_placement()) {
+            let rider_sheet = assets.icon_sheet(&Gamemode::Cube.basename(rider.icon))?;
+            let factor = assets.quality.scale_factor();
+
+            // the cube's layers are moved into place and drawn along with the vehicle's, so they're only resampled once
+            let placement_matrix = Affine::translate(placement.offset.0 * factor, -placement.offset.1 * factor)
+                .then_after(&Affine::scale(placement.scale, placement.scale));
+            let rider_layers: Vec<PlacedLayer> = normal_layers(Gamemode::Cube, rider.icon, &rider.colors.resolve()?, &rider_sheet)?.layers.into_iter()
+                .map(|layer| PlacedLayer { matrix: placement_matrix.then_after(&layer.matrix), ..layer })
+                .collect();
+
+            let index = if placement.in_front { layers.layers.len() } else { layers.glow_count };
+            if let Some(snap_to) = layers.snap_to.as_mut().filter(|snap_to| **snap_to >= index) {
+                *snap_to += rider_layers.len();
+            }
+            layers.layers.splice(index..index, rider_layers);
+        }
+
+        layers
+    };
+
+    // every layer is scaled as it's drawn, rather than resizing the finished image
+    let scale = match options.size {
+        OutputSize::Content | OutputSize::Exact(..) => check_scale(options.scale)?,
+        OutputSize::Fit(width, height) | OutputSize::Fill(width, height) => {
+            let (content_width, content_height) = content_size(&layers.layers, options.anchored);
+            let (scale_x, scale_y) = ((width - options.padding * 2) as f32 / content_width, (height - options.padding * 2) as f32 / content_height);
+            if matches!(options.size, OutputSize::Fit(..)) { scale_x.min(scale_y) } else { scale_x.max(scale_y) }
+        },
+        OutputSize::GameUnits { pixels_per_unit, .. } => check_scale(pixels_per_unit)? / assets.quality.scale_factor()
+    };
+
+    let rendered = composite_layers(layers, scale, options.filter)?;
+    let rendered = if options.anchored { crop_anchored(rendered) } else { crop_whitespace(rendered) };
+
+    return finish(rendered, options);
+}
+
+/// Renders every frame of the animation `options` describes, in order, eg. to pass along to [`crate::encoder::encode_animation`]. The frame set in `options` is ignored.
+///
+/// Turn on [`anchored`](RenderOptions::anchored) to keep the icon from jumping around between frames.
+pub fn render_animation(assets: &IconAssets, options: &RenderOptions) -> Result<Vec<DynamicImage>, RenderError> {
+    let animation = assets.animations(options.gamemode)
+        .and_then(|animations| animations.get(&options.animation))
+        .ok_or_else(|| RenderError::MissingAnimation { animation: options.animation.clone(), frame: options.animation_frame })?;
+
+    return animation.frames.iter()
+        .map(|frame| render(assets, &options.clone().animation(&options.animation, frame.index)))
+        .collect();
+}
+
+/// Renders out an icon from positional arguments. Prefer [`render`], which this is a thin wrapper around.
+///
+/// `gamemode_str` is parsed as a [`Gamemode`], eg. `cube`, `ship` or `ufo`
+#[allow(clippy::too_many_arguments)]
+pub fn render_icon(gamemode_str: &str, icon: i32, col1: impl Into<Color>, col2: impl Into<Color>, glow: bool, game_sheet_02: LoadedSpritesheet, game_sheet_glow: LoadedSpritesheet, robot_animations: Animations, spider_animations: Animations) -> Result<DynamicImage, RenderError> {
+    let assets = IconAssets::new(game_sheet_02, game_sheet_glow, robot_animations, spider_animations);
+    let options = RenderOptions::new(gamemode_str.parse::<Gamemode>()?, icon)
+        .primary_color(col1.into())
+        .secondary_color(col2.into())
+        .glow(glow);
+
+    return render(&assets, &options);
+}